@@ -0,0 +1,115 @@
+//! Done tasks moved out of a list into the archive next to it, see
+//! `store::archive_path`. Archived tasks keep their ids.
+
+use chrono::{Days, NaiveDate};
+
+use crate::{dependencies, subtasks, Task};
+
+/// Take the done tasks out of `tasks`, only the ones due at least
+/// `older_than` days before `today` when it is given. A task stays while one
+/// of its subtasks stays, so no subtask loses its parent. Returns the tasks
+/// to archive.
+pub fn take_archivable(
+    tasks: &mut Vec<Task>,
+    older_than: Option<u32>,
+    today: NaiveDate,
+) -> Result<Vec<Task>, String> {
+    let cutoff = match older_than {
+        Some(days) => Some(
+            today
+                .checked_sub_days(Days::new(days as u64))
+                .ok_or(format!("Invalid age: {} days is before the earliest date", days))?,
+        ),
+        None => None,
+    };
+    let mut archivable: Vec<u32> = tasks
+        .iter()
+        .filter(|task| task.done && cutoff.is_none_or(|cutoff| task.date <= cutoff))
+        .map(|task| task.id)
+        .collect();
+
+    loop {
+        let staying: Vec<u32> = archivable
+            .iter()
+            .copied()
+            .filter(|id| {
+                subtasks::children_of(tasks, *id)
+                    .iter()
+                    .any(|child| !archivable.contains(child))
+            })
+            .collect();
+        if staying.is_empty() {
+            break;
+        }
+        archivable.retain(|id| !staying.contains(id));
+    }
+
+    // archived tasks are done, nothing has to wait for them any more
+    dependencies::forget(tasks, &archivable);
+    let (archived, kept): (Vec<Task>, Vec<Task>) =
+        tasks.drain(..).partition(|task| archivable.contains(&task.id));
+    *tasks = kept;
+    Ok(archived)
+}
+
+/// Put an archived or deleted task back into `tasks`. It gets `next_id` when
+/// its id was given to another task since, and becomes a top level task when
+/// its parent is not in the list. Returns the id the task has now.
+pub fn restore(tasks: &mut Vec<Task>, mut task: Task, next_id: u32) -> u32 {
+    if tasks.iter().any(|existing| existing.id == task.id) {
+        task.id = next_id;
+    }
+    if let Some(parent) = task.parent {
+        if !tasks.iter().any(|existing| existing.id == parent) {
+            task.parent = None;
+        }
+    }
+    task.depends_on
+        .retain(|dependency| tasks.iter().any(|existing| existing.id == *dependency));
+    let id = task.id;
+    tasks.push(task);
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 18).unwrap()
+    }
+
+    fn ids(tasks: &[Task]) -> Vec<u32> {
+        tasks.iter().map(|task| task.id).collect()
+    }
+
+    #[test]
+    fn takes_old_done_tasks_but_keeps_parents_of_open_subtasks() {
+        let mut tasks = vec![
+            testing::task(1, "old", "2026-09-01"),
+            testing::task(2, "recent", "2026-10-17"),
+            testing::task(3, "open", "2026-09-01"),
+            testing::task(4, "parent", "2026-09-01"),
+            testing::task(5, "open subtask", "2026-09-01"),
+        ];
+        for index in [0, 1, 3] {
+            tasks[index].done = true;
+        }
+        tasks[4].parent = Some(4);
+        tasks[2].depends_on = vec![1];
+
+        let archived = take_archivable(&mut tasks, Some(30), today()).unwrap();
+        assert_eq!(ids(&archived), vec![1]);
+        assert_eq!(ids(&tasks), vec![2, 3, 4, 5]);
+        assert!(tasks[1].depends_on.is_empty());
+    }
+
+    #[test]
+    fn rejects_ages_before_the_earliest_date() {
+        let mut tasks = vec![testing::task(1, "old", "2026-09-01")];
+        tasks[0].done = true;
+        assert!(take_archivable(&mut tasks, Some(4000000000), today()).is_err());
+        assert_eq!(ids(&tasks), vec![1]);
+    }
+}
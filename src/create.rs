@@ -1,38 +1,48 @@
-use chrono::NaiveDate;
-
-use crate::{Task, DEFAULT_TIME};
-
+use crate::dates::{self, DateSettings};
+use crate::priority::{self, Priority};
+use crate::recurrence;
+use crate::{tags, Task};
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_task(
     name: String,
     description: Option<String>,
     date: String,
     time: Option<String>,
     tags: Option<Vec<String>>,
+    priority: Option<String>,
+    repeat: Option<String>,
+    parent: Option<u32>,
     generated_id: u32,
-) -> Option<Task> {
-    let date_parsed = NaiveDate::parse_from_str(&date, "%m/%d/%y");
-    if date_parsed.is_err() {
-        eprintln!("Invalid date format: {} (expected mm/dd/yy)", date);
-        return None;
+    settings: &DateSettings,
+) -> Result<Task, String> {
+    // a `!` to `!!!!` word in the name is a priority unless one was given
+    let (name, shorthand) = priority::split_shorthand(&name);
+    let priority = match priority {
+        Some(priority) => priority.parse::<Priority>()?,
+        None => shorthand.unwrap_or_default(),
+    };
+    if name.is_empty() {
+        return Err(String::from("Invalid name: the name can not be empty"));
     }
-
-    if let Some(time) = &time {
-        let time_parsed = chrono::NaiveTime::parse_from_str(&time, "%I:%M%p");
-        if time_parsed.is_err() {
-            eprintln!("Invalid time format: {} (expected hh:mm[am|pm])", time);
-            return None;
-        }
+    let date = dates::parse_date_input(&date, settings)?;
+    let recurrence = recurrence::parse_recurrence(&repeat.unwrap_or_default())?;
+    let time = match time {
+        Some(time) => dates::parse_time_input(&time, settings)?,
+        None => None,
     };
 
-    Some(Task {
+    Ok(Task {
         id: generated_id,
         name,
         date,
-        time: time.unwrap_or(String::from(DEFAULT_TIME)),
-        description: description.unwrap_or(String::from("")),
+        time,
+        description: description.unwrap_or_default().trim().to_string(),
         done: false,
-        tags: tags.unwrap_or(Vec::new()),
+        tags: tags::normalize_tags(tags.unwrap_or_default()),
+        priority,
+        recurrence,
+        parent,
+        depends_on: Vec::new(),
     })
 }
-
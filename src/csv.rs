@@ -0,0 +1,138 @@
+//! Minimal RFC 4180 style reader and writer used for the task list files.
+//!
+//! Fields are separated by `,` and records by a line break. A field that
+//! contains a separator, a quote or a line break is wrapped in double quotes
+//! and any quote inside of it is escaped by doubling it (`"` -> `""`).
+
+/// Parse the whole input into a list of records.
+pub fn parse_records(input: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records: Vec<Vec<String>> = Vec::new();
+    let mut record: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    // true once the current field was opened with a quote, used to reject
+    // garbage between a closing quote and the next separator
+    let mut was_quoted = false;
+    let mut line = 1;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() && !was_quoted => {
+                in_quotes = true;
+                was_quoted = true;
+            }
+            ',' => {
+                record.push(std::mem::take(&mut field));
+                was_quoted = false;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+                was_quoted = false;
+                line += 1;
+            }
+            _ if was_quoted => {
+                return Err(format!(
+                    "Unexpected character '{}' after closing quote on line {}",
+                    c, line
+                ));
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(format!("Unterminated quoted field on line {}", line));
+    }
+
+    // the last record does not need a trailing line break
+    if !field.is_empty() || !record.is_empty() || was_quoted {
+        record.push(field);
+        records.push(record);
+    }
+
+    // blank lines are not records
+    records.retain(|record| !(record.len() == 1 && record[0].is_empty()));
+
+    Ok(records)
+}
+
+/// Quote a single field if it needs it.
+pub fn escape_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) || field.starts_with(' ') || field.ends_with(' ') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Format one record, including its trailing line break.
+pub fn format_record<S: AsRef<str>>(fields: &[S]) -> String {
+    let fields: Vec<String> = fields.iter().map(|field| escape_field(field.as_ref())).collect();
+    format!("{}\n", fields.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_fields_round_trip() {
+        let fields = [
+            "1",
+            "Hello, \"world\"",
+            "multi\nline, desc",
+            "",
+            " padded ",
+            "crlf\r\nline",
+            "plain",
+        ];
+        let formatted = format_record(&fields);
+        assert_eq!(parse_records(&formatted).unwrap(), vec![fields.to_vec()]);
+    }
+
+    #[test]
+    fn only_fields_that_need_it_are_quoted() {
+        assert_eq!(escape_field("plain text"), "plain text");
+        assert_eq!(escape_field("a,b"), "\"a,b\"");
+        assert_eq!(escape_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_field(" lead"), "\" lead\"");
+        assert_eq!(format_record(&["a", "b,c", ""]), "a,\"b,c\",\n");
+    }
+
+    #[test]
+    fn reads_crlf_blank_lines_and_a_missing_final_line_break() {
+        let records = parse_records("id,name\r\n1,a\r\n\r\n2,\"b\"").unwrap();
+        assert_eq!(records, vec![vec!["id", "name"], vec!["1", "a"], vec!["2", "b"]]);
+    }
+
+    #[test]
+    fn rejects_broken_quoting() {
+        assert_eq!(
+            parse_records("1,\"open\n2,b\n").unwrap_err(),
+            "Unterminated quoted field on line 3"
+        );
+        assert_eq!(
+            parse_records("1,\"a\"b\n").unwrap_err(),
+            "Unexpected character 'b' after closing quote on line 1"
+        );
+    }
+}
@@ -0,0 +1,389 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Datelike, Days, Duration, Local, Locale, Months, NaiveDate, NaiveTime, Timelike, Weekday};
+
+use crate::KaskConfig;
+
+/// Date formats accepted on the command line besides the configured one.
+const INPUT_DATE_FORMATS: [&str; 1] = ["%Y-%m-%d"];
+
+/// Time formats accepted on the command line besides the configured one.
+const INPUT_TIME_FORMATS: [&str; 3] = ["%I:%M%P", "%I:%M %p", "%H:%M"];
+
+/// How dates and times are read from the command line and shown in tables.
+/// The csv task lists always use `DATE_FORMAT` and `TIME_FORMAT`.
+#[derive(Clone, Debug)]
+pub struct DateSettings {
+    pub date_format: String,
+    pub time_format: String,
+    pub week_start: Weekday,
+    pub locale: Option<Locale>,
+}
+
+impl DateSettings {
+    pub fn from_config(config: &KaskConfig) -> Result<DateSettings, String> {
+        let locale = match &config.locale {
+            Some(locale) => Some(
+                Locale::try_from(locale.as_str()).map_err(|_| format!("Unknown locale: {}", locale))?,
+            ),
+            None => None,
+        };
+        let settings = DateSettings {
+            date_format: config.date_format.clone(),
+            time_format: config.time_format.clone(),
+            week_start: config.week_start,
+            locale,
+        };
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Make sure both formats can be used to read back what they write.
+    pub fn validate(&self) -> Result<(), String> {
+        let is_valid = |format: &str| StrftimeItems::new(format).all(|item| item != Item::Error);
+
+        let sample_date = NaiveDate::from_ymd_opt(2026, 12, 31).unwrap();
+        let formatted_date = sample_date.format(&self.date_format).to_string();
+        if !is_valid(&self.date_format)
+            || NaiveDate::parse_from_str(&formatted_date, &self.date_format) != Ok(sample_date)
+        {
+            return Err(format!(
+                "Invalid date format: {} (it needs a day, a month and a year, for example %d.%m.%Y)",
+                self.date_format
+            ));
+        }
+
+        let sample_time = NaiveTime::from_hms_opt(23, 45, 0).unwrap();
+        let formatted_time = sample_time.format(&self.time_format).to_string();
+        if !is_valid(&self.time_format)
+            || NaiveTime::parse_from_str(&formatted_time, &self.time_format) != Ok(sample_time)
+        {
+            return Err(format!(
+                "Invalid time format: {} (it needs an hour and minutes, for example %H:%M)",
+                self.time_format
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn format_date(&self, date: &NaiveDate) -> String {
+        self.format_localized(date, &self.date_format)
+    }
+
+    pub fn format_time(&self, time: &Option<NaiveTime>) -> String {
+        match time {
+            Some(time) => time.format(&self.time_format).to_string(),
+            None => String::new(),
+        }
+    }
+
+    fn format_localized(&self, date: &NaiveDate, format: &str) -> String {
+        match self.locale {
+            Some(locale) => date.format_localized(format, locale).to_string(),
+            None => date.format(format).to_string(),
+        }
+    }
+
+    /// First day of the week that `date` falls in.
+    pub fn start_of_week(&self, date: NaiveDate) -> NaiveDate {
+        date - Duration::days(days_from(self.week_start, date.weekday()))
+    }
+}
+
+impl Default for DateSettings {
+    fn default() -> DateSettings {
+        DateSettings {
+            date_format: crate::DATE_FORMAT.to_string(),
+            time_format: crate::TIME_FORMAT.to_string(),
+            week_start: Weekday::Mon,
+            locale: None,
+        }
+    }
+}
+
+/// Turn a strftime format into the kind of hint shown in error messages,
+/// `%m/%d/%y` becomes `mm/dd/yy`.
+fn format_hint(format: &str) -> String {
+    format
+        .replace("%d", "dd")
+        .replace("%m", "mm")
+        .replace("%Y", "yyyy")
+        .replace("%y", "yy")
+        .replace("%H", "hh")
+        .replace("%I", "hh")
+        .replace("%M", "mm")
+        .replace("%P", "[am|pm]")
+        .replace("%p", "[AM|PM]")
+}
+
+/// Parse a date given on the command line, relative dates are resolved
+/// against today.
+pub fn parse_date_input(input: &str, settings: &DateSettings) -> Result<NaiveDate, String> {
+    parse_date_relative(input, Local::now().date_naive(), settings)
+}
+
+/// Parse a date given on the command line, resolving relative expressions
+/// such as `tomorrow`, `next fri` or `+3d` against `today`.
+pub fn parse_date_relative(
+    input: &str,
+    today: NaiveDate,
+    settings: &DateSettings,
+) -> Result<NaiveDate, String> {
+    let input = input.trim().to_lowercase();
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let invalid = || {
+        format!(
+            "Invalid date format: {} (expected {}, yyyy-mm-dd, today, tomorrow, a weekday, \
+             next week, +3d, in 2 weeks, eow, eom or eoy)",
+            input,
+            format_hint(&settings.date_format)
+        )
+    };
+
+    // a two digit year format also takes four digit years
+    let long_year_format = settings.date_format.replace("%y", "%Y");
+    let formats = [settings.date_format.as_str(), long_year_format.as_str()];
+    if let Some(date) = formats
+        .iter()
+        .chain(INPUT_DATE_FORMATS.iter())
+        .find_map(|format| NaiveDate::parse_from_str(&input, format).ok())
+    {
+        return Ok(date);
+    }
+
+    // a date without its year (mm/dd, dd.mm) is the next time that day comes around
+    let separator = long_year_format
+        .strip_suffix("%Y")
+        .and_then(|format| format.chars().last());
+    if let Some(separator) = separator {
+        let with_year = format!("{}{}{}", input, separator, today.year());
+        if let Ok(date) = NaiveDate::parse_from_str(&with_year, &long_year_format) {
+            return Ok(if date < today {
+                date.with_year(today.year() + 1).unwrap_or(date)
+            } else {
+                date
+            });
+        }
+    }
+
+    let date = match words.as_slice() {
+        ["today"] | ["tod"] => Some(today),
+        ["tomorrow"] | ["tmr"] | ["tom"] => today.succ_opt(),
+        ["yesterday"] => today.pred_opt(),
+        ["eow"] | ["end", "of", "week"] => Some(settings.start_of_week(today) + Duration::days(6)),
+        ["eom"] | ["end", "of", "month"] => end_of_month(today),
+        ["eoy"] | ["end", "of", "year"] => NaiveDate::from_ymd_opt(today.year(), 12, 31),
+        ["next", "week"] => Some(settings.start_of_week(today) + Duration::days(7)),
+        ["next", "month"] => today.with_day(1).map(|first| first + Months::new(1)),
+        ["next", "year"] => NaiveDate::from_ymd_opt(today.year() + 1, 1, 1),
+        ["next", weekday] => parse_weekday(weekday).map(|weekday| {
+            let next_week = settings.start_of_week(today) + Duration::days(7);
+            next_week + Duration::days(days_from(settings.week_start, weekday))
+        }),
+        ["in", amount, unit] => {
+            let amount = amount.parse::<i64>().map_err(|_| invalid())?;
+            add_offset(today, amount, unit)
+        }
+        [offset] if offset.starts_with('+') || offset.starts_with('-') => {
+            let split = offset.find(|c: char| c.is_alphabetic()).ok_or_else(invalid)?;
+            let (amount, unit) = offset.split_at(split);
+            let amount = amount.parse::<i64>().map_err(|_| invalid())?;
+            add_offset(today, amount, unit)
+        }
+        [weekday] => parse_weekday(weekday).map(|weekday| next_weekday(today, weekday)),
+        _ => None,
+    };
+
+    date.ok_or_else(invalid)
+}
+
+/// Parse a time given on the command line. An empty value means no time.
+pub fn parse_time_input(input: &str, settings: &DateSettings) -> Result<Option<NaiveTime>, String> {
+    let input = input.trim().to_lowercase();
+    if input.is_empty() {
+        return Ok(None);
+    }
+
+    let time = match input.as_str() {
+        "noon" | "midday" => NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => NaiveTime::from_hms_opt(0, 0, 0),
+        "morning" => NaiveTime::from_hms_opt(9, 0, 0),
+        "evening" => NaiveTime::from_hms_opt(18, 0, 0),
+        "eod" => NaiveTime::from_hms_opt(17, 0, 0),
+        "now" => {
+            let now = Local::now();
+            NaiveTime::from_hms_opt(now.hour(), now.minute(), 0)
+        }
+        _ => std::iter::once(settings.time_format.as_str())
+            .chain(INPUT_TIME_FORMATS.iter().copied())
+            .find_map(|format| NaiveTime::parse_from_str(&input, format).ok())
+            .or_else(|| parse_hour_only(&input)),
+    };
+
+    time.map(Some).ok_or(format!(
+        "Invalid time format: {} (expected {}, 9am, 17:30, noon or midnight)",
+        input,
+        format_hint(&settings.time_format)
+    ))
+}
+
+/// Parse times such as `9am` or `5 pm` that have no minutes, chrono always
+/// wants a minute field.
+fn parse_hour_only(input: &str) -> Option<NaiveTime> {
+    let (hour, pm) = match input.strip_suffix("am") {
+        Some(hour) => (hour, false),
+        None => (input.strip_suffix("pm")?, true),
+    };
+    let hour = hour.trim().parse::<u32>().ok().filter(|hour| (1..=12).contains(hour))?;
+    NaiveTime::from_hms_opt(hour % 12 + if pm { 12 } else { 0 }, 0, 0)
+}
+
+/// Describe a resolved date and time so the user can check what was stored.
+pub fn describe(date: &NaiveDate, time: &Option<NaiveTime>, settings: &DateSettings) -> String {
+    let date = settings.format_localized(date, &format!("%a {}", settings.date_format));
+    match time {
+        Some(_) => format!("{} at {}", date, settings.format_time(time)),
+        None => date,
+    }
+}
+
+pub fn parse_weekday(input: &str) -> Option<Weekday> {
+    match input {
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tues" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thur" | "thurs" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        "sun" | "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// The next day that falls on `weekday`, today counts.
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    today + Duration::days(days_from(today.weekday(), weekday))
+}
+
+/// Number of days to go forward from `from` to reach `to`, between 0 and 6.
+pub fn days_from(from: Weekday, to: Weekday) -> i64 {
+    ((to.num_days_from_monday() + 7 - from.num_days_from_monday()) % 7) as i64
+}
+
+pub fn end_of_month(date: NaiveDate) -> Option<NaiveDate> {
+    (date.with_day(1)? + Months::new(1)).pred_opt()
+}
+
+/// `date` moved by `amount` units, `None` when that leaves the supported range.
+fn add_offset(date: NaiveDate, amount: i64, unit: &str) -> Option<NaiveDate> {
+    let days = |days: i64| -> Option<NaiveDate> {
+        let offset = Days::new(days.unsigned_abs());
+        if days >= 0 {
+            date.checked_add_days(offset)
+        } else {
+            date.checked_sub_days(offset)
+        }
+    };
+    let months = |months: i64| -> Option<NaiveDate> {
+        let offset = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+        if months >= 0 {
+            date.checked_add_months(offset)
+        } else {
+            date.checked_sub_months(offset)
+        }
+    };
+
+    match unit {
+        "d" | "day" | "days" => days(amount),
+        "w" | "week" | "weeks" => days(amount.checked_mul(7)?),
+        "m" | "month" | "months" => months(amount),
+        "y" | "year" | "years" => months(amount.checked_mul(12)?),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(input: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(input, "%Y-%m-%d").unwrap()
+    }
+
+    /// `input` read on sunday 2026-10-18.
+    fn relative(input: &str, settings: &DateSettings) -> Result<NaiveDate, String> {
+        parse_date_relative(input, date("2026-10-18"), settings)
+    }
+
+    #[test]
+    fn reads_relative_expressions() {
+        let settings = DateSettings::default();
+        for (input, expected) in [
+            ("today", "2026-10-18"),
+            ("Tomorrow", "2026-10-19"),
+            ("yesterday", "2026-10-17"),
+            ("eow", "2026-10-18"),
+            ("end of month", "2026-10-31"),
+            ("eoy", "2026-12-31"),
+            ("next week", "2026-10-19"),
+            ("next month", "2026-11-01"),
+            ("next fri", "2026-10-23"),
+            ("sun", "2026-10-18"),
+            ("friday", "2026-10-23"),
+            ("+3d", "2026-10-21"),
+            ("-1w", "2026-10-11"),
+            ("+1y", "2027-10-18"),
+            ("in 2 weeks", "2026-11-01"),
+            ("in 2 months", "2026-12-18"),
+        ] {
+            assert_eq!(relative(input, &settings), Ok(date(expected)), "{}", input);
+        }
+    }
+
+    #[test]
+    fn the_week_starts_on_the_configured_day() {
+        let settings = DateSettings {
+            week_start: Weekday::Sun,
+            ..DateSettings::default()
+        };
+        assert_eq!(relative("eow", &settings), Ok(date("2026-10-24")));
+        assert_eq!(relative("next week", &settings), Ok(date("2026-10-25")));
+        assert_eq!(relative("next mon", &settings), Ok(date("2026-10-26")));
+    }
+
+    #[test]
+    fn reads_absolute_dates() {
+        let settings = DateSettings::default();
+        assert_eq!(relative("2027-03-04", &settings), Ok(date("2027-03-04")));
+        assert_eq!(relative("10/20/26", &settings), Ok(date("2026-10-20")));
+        assert_eq!(relative("10/20/2031", &settings), Ok(date("2031-10-20")));
+        // without a year it is the next time the day comes around
+        assert_eq!(relative("10/20", &settings), Ok(date("2026-10-20")));
+        assert_eq!(relative("01/05", &settings), Ok(date("2027-01-05")));
+    }
+
+    #[test]
+    fn months_keep_the_day_when_they_can() {
+        let settings = DateSettings::default();
+        let today = date("2027-01-31");
+        assert_eq!(parse_date_relative("+1m", today, &settings), Ok(date("2027-02-28")));
+        assert_eq!(parse_date_relative("-2m", today, &settings), Ok(date("2026-11-30")));
+    }
+
+    #[test]
+    fn rejects_offsets_past_the_calendar() {
+        let settings = DateSettings::default();
+        for input in [
+            "+99999999999999d",
+            "-99999999999999d",
+            "in 9999999999999999 weeks",
+            "+99999999999y",
+            "in 4294967296 months",
+            "in 99999999999999999999 days",
+            "+3x",
+            "someday",
+        ] {
+            assert!(relative(input, &settings).is_err(), "{} should not parse", input);
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Tasks that have to wait for other tasks. A task lists the ids it depends on
+//! in `Task::depends_on` and is blocked until every one of them is done.
+
+use clap::Subcommand;
+
+use crate::Task;
+
+#[derive(Subcommand, Debug)]
+pub enum DependCommand {
+    /// Make a task wait for other tasks
+    Add {
+        id: u32,
+        /// Ids of the tasks it depends on
+        #[clap(required = true)]
+        on: Vec<u32>,
+    },
+    /// Stop a task from waiting for other tasks
+    Remove {
+        id: u32,
+        /// Ids of the tasks it no longer depends on
+        #[clap(required = true)]
+        on: Vec<u32>,
+    },
+}
+
+/// Ids of the open tasks `task` is waiting for. Dependencies on tasks that no
+/// longer exist do not block anything.
+pub fn blocked_by(task: &Task, tasks: &[Task]) -> Vec<u32> {
+    task.depends_on
+        .iter()
+        .copied()
+        .filter(|id| tasks.iter().any(|other| other.id == *id && !other.done))
+        .collect()
+}
+
+pub fn is_blocked(task: &Task, tasks: &[Task]) -> bool {
+    !blocked_by(task, tasks).is_empty()
+}
+
+/// The chain of dependencies that leads from `from` to `to`, both included,
+/// or `None` when `from` does not depend on `to` at all.
+fn dependency_path(tasks: &[Task], from: u32, to: u32) -> Option<Vec<u32>> {
+    let mut paths: Vec<Vec<u32>> = vec![vec![from]];
+    let mut seen: Vec<u32> = vec![from];
+    while let Some(path) = paths.pop() {
+        let last = *path.last().unwrap();
+        if last == to {
+            return Some(path);
+        }
+        let depends_on = tasks
+            .iter()
+            .find(|task| task.id == last)
+            .map(|task| task.depends_on.clone())
+            .unwrap_or_default();
+        for id in depends_on {
+            if !seen.contains(&id) {
+                seen.push(id);
+                paths.push([path.clone(), vec![id]].concat());
+            }
+        }
+    }
+    None
+}
+
+/// Make `id` depend on every task in `on`. Nothing changes when one of them
+/// does not exist or would close a loop of tasks waiting on each other.
+pub fn add_dependencies(tasks: &mut [Task], id: u32, on: &[u32]) -> Result<(), String> {
+    if !tasks.iter().any(|task| task.id == id) {
+        return Err(format!("Task {} not found", id));
+    }
+    for dependency in on {
+        if *dependency == id {
+            return Err(format!("Task {} can not depend on itself", id));
+        }
+        if !tasks.iter().any(|task| task.id == *dependency) {
+            return Err(format!("Task {} not found", dependency));
+        }
+        if let Some(path) = dependency_path(tasks, *dependency, id) {
+            let path: Vec<String> = path.iter().map(|id| id.to_string()).collect();
+            return Err(format!(
+                "Task {} can not depend on {}, that would make a cycle: {} -> {}",
+                id,
+                dependency,
+                id,
+                path.join(" -> ")
+            ));
+        }
+    }
+
+    let task = tasks.iter_mut().find(|task| task.id == id).unwrap();
+    for dependency in on {
+        if !task.depends_on.contains(dependency) {
+            task.depends_on.push(*dependency);
+        }
+    }
+    Ok(())
+}
+
+pub fn remove_dependencies(tasks: &mut [Task], id: u32, on: &[u32]) -> Result<(), String> {
+    let task = tasks
+        .iter_mut()
+        .find(|task| task.id == id)
+        .ok_or(format!("Task {} not found", id))?;
+    if let Some(missing) = on.iter().find(|dependency| !task.depends_on.contains(dependency)) {
+        return Err(format!("Task {} does not depend on {}", id, missing));
+    }
+    task.depends_on.retain(|dependency| !on.contains(dependency));
+    Ok(())
+}
+
+/// Drop the dependencies on the `removed` tasks so nothing waits for a task
+/// that was deleted. Returns the ids of the tasks that changed.
+pub fn forget(tasks: &mut [Task], removed: &[u32]) -> Vec<u32> {
+    let mut changed: Vec<u32> = Vec::new();
+    for task in tasks.iter_mut() {
+        let count = task.depends_on.len();
+        task.depends_on.retain(|dependency| !removed.contains(dependency));
+        if task.depends_on.len() != count {
+            changed.push(task.id);
+        }
+    }
+    changed
+}
+
+/// Ids of the open tasks that were waiting on one of `completed` and are not
+/// blocked any more.
+pub fn unblocked_by(tasks: &[Task], completed: &[u32]) -> Vec<u32> {
+    tasks
+        .iter()
+        .filter(|task| !task.done)
+        .filter(|task| task.depends_on.iter().any(|id| completed.contains(id)))
+        .filter(|task| !is_blocked(task, tasks))
+        .map(|task| task.id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn tasks() -> Vec<Task> {
+        (1..=5).map(|id| testing::task(id, &format!("task {}", id), "2026-10-20")).collect()
+    }
+
+    fn depends_on(tasks: &[Task], id: u32) -> Vec<u32> {
+        tasks.iter().find(|task| task.id == id).unwrap().depends_on.clone()
+    }
+
+    #[test]
+    fn refuses_cycles_and_names_their_path() {
+        let mut tasks = tasks();
+        add_dependencies(&mut tasks, 4, &[3]).unwrap();
+        assert_eq!(
+            add_dependencies(&mut tasks, 3, &[4]),
+            Err(String::from("Task 3 can not depend on 4, that would make a cycle: 3 -> 4 -> 3"))
+        );
+        add_dependencies(&mut tasks, 3, &[2]).unwrap();
+        add_dependencies(&mut tasks, 2, &[1]).unwrap();
+        assert_eq!(
+            add_dependencies(&mut tasks, 1, &[5, 4]),
+            Err(String::from(
+                "Task 1 can not depend on 4, that would make a cycle: 1 -> 4 -> 3 -> 2 -> 1"
+            ))
+        );
+        // nothing changes when one of the dependencies is refused
+        assert!(depends_on(&tasks, 1).is_empty());
+        assert!(add_dependencies(&mut tasks, 5, &[5]).is_err());
+        assert!(add_dependencies(&mut tasks, 5, &[9]).is_err());
+
+        // a task two others wait for is not a cycle
+        add_dependencies(&mut tasks, 5, &[3, 4, 3]).unwrap();
+        assert_eq!(depends_on(&tasks, 5), vec![3, 4]);
+    }
+
+    #[test]
+    fn only_open_dependencies_block() {
+        let mut tasks = tasks();
+        add_dependencies(&mut tasks, 3, &[1, 2]).unwrap();
+        tasks[0].done = true;
+        assert_eq!(blocked_by(&tasks[2], &tasks), vec![2]);
+        assert!(is_blocked(&tasks[2], &tasks));
+
+        tasks[1].done = true;
+        assert!(!is_blocked(&tasks[2], &tasks));
+        assert_eq!(unblocked_by(&tasks, &[2]), vec![3]);
+    }
+
+    #[test]
+    fn deleted_tasks_are_forgotten() {
+        let mut tasks = tasks();
+        add_dependencies(&mut tasks, 3, &[1, 2]).unwrap();
+        add_dependencies(&mut tasks, 4, &[2]).unwrap();
+        add_dependencies(&mut tasks, 5, &[1]).unwrap();
+
+        tasks.retain(|task| task.id != 2);
+        assert_eq!(forget(&mut tasks, &[2]), vec![3, 4]);
+        assert_eq!(depends_on(&tasks, 3), vec![1]);
+        assert!(depends_on(&tasks, 4).is_empty());
+        assert_eq!(depends_on(&tasks, 5), vec![1]);
+        assert!(forget(&mut tasks, &[2]).is_empty());
+
+        assert!(remove_dependencies(&mut tasks, 3, &[2]).is_err());
+        remove_dependencies(&mut tasks, 3, &[1]).unwrap();
+        assert!(depends_on(&tasks, 3).is_empty());
+    }
+}
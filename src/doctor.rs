@@ -0,0 +1,429 @@
+//! `kask doctor` finds the rows of a task list that no longer load, for
+//! example because they were edited by hand or written by an older version
+//! that did not validate dates, and repairs the ones it can.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+
+use chrono::{NaiveDate, NaiveTime};
+
+use crate::ids;
+use crate::list;
+use crate::priority::Priority;
+use crate::recurrence;
+use crate::store::{self, Backend};
+use crate::{csv, utils, Task, DATE_FORMAT, TIME_FORMAT};
+
+/// Date formats that are tried when the stored date does not parse.
+const REPAIR_DATE_FORMATS: [&str; 6] = [
+    DATE_FORMAT,
+    "%m/%d/%Y",
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+    "%m-%d-%y",
+    "%m-%d-%Y",
+];
+
+/// Time formats that are tried when the stored time does not parse.
+const REPAIR_TIME_FORMATS: [&str; 5] = [TIME_FORMAT, "%I:%M %p", "%I%p", "%H:%M", "%H:%M:%S"];
+
+/// A row of a task list before it is turned into a `Task`.
+struct RawTask {
+    /// Where the row is in the file, used in the report
+    location: String,
+    /// The row as it was written, kept in the rejected file
+    source: String,
+    fields: HashMap<String, String>,
+}
+
+fn read_csv_rows(contents: &str) -> Result<Vec<RawTask>, String> {
+    let mut rows: Vec<RawTask> = Vec::new();
+
+    if utils::is_legacy_task_file(contents) {
+        for (number, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields = utils::LEGACY_TASK_FILE_COLUMNS
+                .iter()
+                .zip(line.split(','))
+                .map(|(column, value)| (column.to_string(), value.trim().to_string()))
+                .collect::<HashMap<String, String>>();
+            let fields = if line.split(',').count() == utils::LEGACY_TASK_FILE_COLUMNS.len() {
+                fields
+            } else {
+                HashMap::new()
+            };
+            rows.push(RawTask {
+                location: format!("line {}", number + 1),
+                source: line.to_string(),
+                fields,
+            });
+        }
+        return Ok(rows);
+    }
+
+    let mut records = csv::parse_records(contents)?.into_iter();
+    let header = match records.next() {
+        Some(header) => header,
+        None => return Ok(rows),
+    };
+    for (number, record) in records.enumerate() {
+        let fields = if record.len() == header.len() {
+            header.iter().cloned().zip(record.iter().cloned()).collect()
+        } else {
+            HashMap::new()
+        };
+        rows.push(RawTask {
+            location: format!("record {}", number + 2),
+            source: csv::format_record(&record).trim_end().to_string(),
+            fields,
+        });
+    }
+    Ok(rows)
+}
+
+fn json_value_to_field(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value.clone(),
+        serde_json::Value::Array(values) => values
+            .iter()
+            .map(json_value_to_field)
+            .collect::<Vec<String>>()
+            .join(";"),
+        serde_json::Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+fn read_json_rows(contents: &str, lines: bool) -> Result<Vec<RawTask>, String> {
+    let values: Vec<(String, serde_json::Value)> = if lines {
+        contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(number, line)| {
+                let value = serde_json::from_str(line).unwrap_or(serde_json::Value::Null);
+                (format!("line {}", number + 1), value)
+            })
+            .collect()
+    } else if contents.trim().is_empty() {
+        Vec::new()
+    } else {
+        let values: Vec<serde_json::Value> =
+            serde_json::from_str(contents).map_err(|e| format!("Error parsing file: {}", e))?;
+        values
+            .into_iter()
+            .enumerate()
+            .map(|(number, value)| (format!("task {}", number + 1), value))
+            .collect()
+    };
+
+    Ok(values
+        .into_iter()
+        .map(|(location, value)| {
+            let fields = match &value {
+                serde_json::Value::Object(object) => object
+                    .iter()
+                    .map(|(key, value)| (key.clone(), json_value_to_field(value)))
+                    .collect(),
+                _ => HashMap::new(),
+            };
+            RawTask {
+                location,
+                source: value.to_string(),
+                fields,
+            }
+        })
+        .collect())
+}
+
+/// Turn a raw row back into a task, noting every change made along the way.
+/// Rows that can not be saved without guessing their contents are rejected.
+/// An id of 0 means a new one has to be assigned. Dates are read and written
+/// in the format of the `backend` the row comes from.
+fn repair_task(raw: &RawTask, backend: Backend, fixes: &mut Vec<String>) -> Result<Task, String> {
+    if raw.fields.is_empty() {
+        return Err(String::from("the row does not have the expected fields"));
+    }
+    let field = |name: &str| raw.fields.get(name).map(|value| value.trim()).unwrap_or("");
+
+    let id = match field("id").parse::<u32>() {
+        Ok(id) if id > 0 => id,
+        _ => {
+            fixes.push(format!("invalid id '{}', a new id will be assigned", field("id")));
+            0
+        }
+    };
+
+    let name = field("name").to_string();
+    if name.is_empty() {
+        return Err(String::from("the task has no name"));
+    }
+
+    let json = matches!(backend, Backend::Json | Backend::Jsonl);
+    let stored_date = if json {
+        utils::serde_date::parse(field("date"))
+    } else {
+        utils::parse_stored_date(field("date"))
+    };
+    let date = match stored_date {
+        Ok(date) => date,
+        Err(_) => {
+            let date = REPAIR_DATE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(field("date"), format).ok())
+                .ok_or(format!("invalid date '{}'", field("date")))?;
+            // only the csv lists are limited to two digit years
+            let rewritten = if json {
+                date.format(utils::serde_date::JSON_DATE_FORMAT).to_string()
+            } else {
+                utils::format_stored_date(&date)?
+            };
+            fixes.push(format!("date '{}' rewritten as {}", field("date"), rewritten));
+            date
+        }
+    };
+
+    let time = match utils::parse_stored_time(field("time")) {
+        Ok(time) => time,
+        Err(_) => {
+            let time = REPAIR_TIME_FORMATS
+                .iter()
+                .find_map(|format| NaiveTime::parse_from_str(field("time"), format).ok());
+            match time {
+                Some(_) => fixes.push(format!(
+                    "time '{}' rewritten as {}",
+                    field("time"),
+                    utils::format_stored_time(&time)
+                )),
+                None => fixes.push(format!("invalid time '{}' cleared", field("time"))),
+            }
+            time
+        }
+    };
+
+    let done = match field("done").to_lowercase().as_str() {
+        "true" => true,
+        "false" => false,
+        "yes" | "y" | "1" | "x" | "done" => {
+            fixes.push(format!("done '{}' read as true", field("done")));
+            true
+        }
+        other => {
+            fixes.push(format!("done '{}' read as false", other));
+            false
+        }
+    };
+
+    let priority = field("priority").parse::<Priority>().unwrap_or_else(|_| {
+        fixes.push(format!("invalid priority '{}' cleared", field("priority")));
+        Priority::None
+    });
+    let recurrence = recurrence::parse_recurrence(field("recurrence")).unwrap_or_else(|_| {
+        fixes.push(format!("invalid recurrence '{}' cleared", field("recurrence")));
+        None
+    });
+    let parent = utils::parse_parent(field("parent")).unwrap_or_else(|_| {
+        fixes.push(format!("invalid parent '{}' cleared", field("parent")));
+        None
+    });
+    let depends_on = utils::parse_depends_on(field("depends_on")).unwrap_or_else(|_| {
+        fixes.push(format!("invalid dependencies '{}' cleared", field("depends_on")));
+        Vec::new()
+    });
+
+    Ok(Task {
+        id,
+        name,
+        date,
+        time,
+        description: field("description").to_string(),
+        done,
+        tags: field("tags")
+            .split(';')
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect(),
+        priority,
+        recurrence,
+        parent,
+        depends_on,
+    })
+}
+
+/// Check the task list at `path` and, when `fix` is set, rewrite it with the
+/// repaired rows. Rows that can not be repaired are moved to `<path>.rejected`
+/// and the original file is kept as `<path>.bak`.
+pub fn doctor(path: &str, fix: bool) -> Result<(), String> {
+    let backend = store::detect_backend(path);
+    if backend == Backend::Sqlite {
+        // the database enforces its own types, so loading it is the whole check
+        let tasks = store::open_store(path)?.load()?;
+        println!("{}: {} tasks, no problems found", path, tasks.len());
+        return Ok(());
+    }
+
+    let contents = fs::read_to_string(path).map_err(|e| format!("Error reading file: {}", e))?;
+    let rows = match backend {
+        Backend::Json => read_json_rows(&contents, false)?,
+        Backend::Jsonl => read_json_rows(&contents, true)?,
+        _ => read_csv_rows(&contents)?,
+    };
+
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut rejected: Vec<&RawTask> = Vec::new();
+    let mut problems = 0;
+    for raw in rows.iter() {
+        let mut fixes: Vec<String> = Vec::new();
+        match repair_task(raw, backend, &mut fixes) {
+            Ok(task) => {
+                if !fixes.is_empty() {
+                    problems += 1;
+                    println!("{}: {}", raw.location, fixes.join(", "));
+                }
+                tasks.push(task);
+            }
+            Err(error) => {
+                problems += 1;
+                println!("{}: {}, the row can not be repaired", raw.location, error);
+                rejected.push(raw);
+            }
+        }
+    }
+
+    // give the tasks without a usable id, or with one that is already taken,
+    // a new one
+    let last_id = tasks.iter().map(|task| task.id).max().unwrap_or(0);
+    let mut next_id = ids::next_id_after(path, ids::following(last_id)?)?;
+    let mut seen: HashSet<u32> = HashSet::new();
+    for task in tasks.iter_mut() {
+        if task.id == 0 || !seen.insert(task.id) {
+            if task.id != 0 {
+                problems += 1;
+                println!("task {}: duplicate id, renumbered to {}", task.id, next_id);
+            }
+            task.id = next_id;
+            seen.insert(next_id);
+            next_id = ids::following(next_id)?;
+        }
+    }
+
+    // subtasks of a task that is gone become top level tasks
+    let known: HashSet<u32> = tasks.iter().map(|task| task.id).collect();
+    for task in tasks.iter_mut() {
+        if let Some(parent) = task.parent {
+            if parent == task.id || !known.contains(&parent) {
+                problems += 1;
+                println!("task {}: parent {} not found, moved to the top level", task.id, parent);
+                task.parent = None;
+            }
+        }
+        let missing: Vec<u32> = task
+            .depends_on
+            .iter()
+            .copied()
+            .filter(|id| *id == task.id || !known.contains(id))
+            .collect();
+        if !missing.is_empty() {
+            problems += 1;
+            task.depends_on.retain(|id| !missing.contains(id));
+            let missing = list::format_ids(&missing);
+            println!("task {}: dependencies {} not found, dropped", task.id, missing);
+        }
+    }
+
+    if problems == 0 {
+        println!("{}: {} tasks, no problems found", path, tasks.len());
+        return Ok(());
+    }
+
+    if !fix {
+        println!(
+            "{} problems found in {}, run `kask doctor --fix` to repair them",
+            problems, path
+        );
+        return Ok(());
+    }
+
+    let backup_path = format!("{}.bak", path);
+    fs::copy(path, &backup_path).map_err(|e| format!("Error backing up {}: {}", path, e))?;
+    store::open_store_with_backend(path, backend)?.write_all(&tasks)?;
+    ids::hand_out(path, next_id - 1)?;
+
+    if !rejected.is_empty() {
+        let rejected_path = format!("{}.rejected", path);
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&rejected_path)
+            .map_err(|e| format!("Error opening file: {}", e))?;
+        for raw in rejected.iter() {
+            writeln!(file, "{}", raw.source).map_err(|e| format!("Error writing to file: {}", e))?;
+        }
+        println!("{} rows that could not be repaired were moved to {}", rejected.len(), rejected_path);
+    }
+
+    println!(
+        "Repaired {}, {} tasks kept, the original file was saved as {}",
+        path,
+        tasks.len(),
+        backup_path
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    #[test]
+    fn json_dates_past_the_csv_years_are_healthy() {
+        let dir = TempDir::new();
+        let path = dir.path("tasks.json");
+        let tasks = vec![testing::task(1, "far away", "2099-01-01")];
+        store::open_store(&path).unwrap().write_all(&tasks).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let rows = read_json_rows(&contents, false).unwrap();
+        let mut fixes: Vec<String> = Vec::new();
+        let task = repair_task(&rows[0], Backend::Json, &mut fixes).unwrap();
+        assert!(fixes.is_empty(), "{:?}", fixes);
+        assert_eq!(task, tasks[0]);
+    }
+
+    #[test]
+    fn fixing_a_json_list_keeps_far_future_dates() {
+        let dir = TempDir::new();
+        let path = dir.file(
+            "tasks.jsonl",
+            "{\"id\":1,\"name\":\"far away\",\"date\":\"2099-01-01\",\"done\":false}\n\
+             {\"id\":2,\"name\":\"old date\",\"date\":\"01/02/2101\",\"done\":\"yes\"}\n",
+        );
+
+        doctor(&path, true).unwrap();
+        let tasks = store::open_store(&path).unwrap().load().unwrap();
+        let dates: Vec<String> = tasks.iter().map(|task| task.date.to_string()).collect();
+        assert_eq!(dates, vec!["2099-01-01", "2101-01-02"]);
+        assert!(tasks[1].done);
+        assert!(!Path::new(&format!("{}.rejected", path)).exists());
+    }
+
+    #[test]
+    fn csv_rows_outside_the_two_digit_years_are_rejected() {
+        let raw = RawTask {
+            location: String::from("record 2"),
+            source: String::new(),
+            fields: [("id", "1"), ("name", "far away"), ("date", "2099-01-01"), ("done", "false")]
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        };
+        let mut fixes: Vec<String> = Vec::new();
+        assert!(repair_task(&raw, Backend::Csv, &mut fixes).is_err());
+    }
+}
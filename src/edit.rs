@@ -1,7 +1,13 @@
-use crate::Task;
+use crate::dates::{self, DateSettings};
+use chrono::NaiveDate;
 
+use crate::priority::Priority;
+use crate::recurrence;
+use crate::{tags, Task};
+
+#[allow(clippy::too_many_arguments)]
 pub fn edit_task(
-    tasks: &mut Vec<Task>,
+    tasks: &mut [Task],
     id: u32,
     name: Option<String>,
     description: Option<String>,
@@ -9,40 +15,101 @@ pub fn edit_task(
     time: Option<String>,
     done: Option<bool>,
     tags: Option<Vec<String>>,
-) -> Result<(), String>{
+    priority: Option<String>,
+    repeat: Option<String>,
+    settings: &DateSettings,
+) -> Result<(), String> {
     let task = tasks.iter_mut().find(|task| task.id == id).ok_or("Task not found")?;
 
+    // validate every field before touching the task so a bad value never
+    // leaves it half updated
+    let mut errors: Vec<String> = Vec::new();
+    let name = name.map(|name| name.trim().to_string());
+    if name.as_ref().is_some_and(|name| name.is_empty()) {
+        errors.push(String::from("Invalid name: the name can not be empty"));
+    }
+    let date = match date.map(|date| dates::parse_date_input(&date, settings)).transpose() {
+        Ok(date) => date,
+        Err(error) => {
+            errors.push(error);
+            None
+        }
+    };
+    let time = match time.map(|time| dates::parse_time_input(&time, settings)).transpose() {
+        Ok(time) => time,
+        Err(error) => {
+            errors.push(error);
+            None
+        }
+    };
+    let priority = match priority.map(|priority| priority.parse::<Priority>()).transpose() {
+        Ok(priority) => priority,
+        Err(error) => {
+            errors.push(error);
+            None
+        }
+    };
+    let recurrence = match repeat.map(|repeat| recurrence::parse_recurrence(&repeat)).transpose() {
+        Ok(recurrence) => recurrence,
+        Err(error) => {
+            errors.push(error);
+            None
+        }
+    };
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+
     if let Some(name) = name {
-        task.name = name.trim().to_string();
+        task.name = name;
     }
     if let Some(date) = date {
-        task.date = date.trim().to_string();
+        task.date = date;
     }
     if let Some(description) = description {
         task.description = description.trim().to_string();
     }
     if let Some(time) = time {
-        task.time = time.trim().to_string();
+        task.time = time;
     }
     if let Some(tags) = tags {
-        task.tags = tags;
+        task.tags = tags::normalize_tags(tags);
     }
     if let Some(done) = done {
         task.done = done;
     }
+    if let Some(priority) = priority {
+        task.priority = priority;
+    }
+    if let Some(recurrence) = recurrence {
+        task.recurrence = recurrence;
+    }
 
     Ok(())
 }
 
-pub fn complete_task(tasks: &mut Vec<Task>, id: u32) {
-    for task in tasks {
-        if task.id == id {
-            task.done = true;
-            break;
-        }
-    }
-}
+/// Mark a task as done. When the task repeats, its next occurrence is
+/// returned with `next_id` as its id and the rule moves over to it, so
+/// completing the same occurrence twice does not create two new ones. A rule
+/// with no date left in the supported range ends the series.
+pub fn complete_task(
+    tasks: &mut [Task],
+    id: u32,
+    next_id: u32,
+    today: NaiveDate,
+) -> Result<(&Task, Option<Task>), String> {
+    let task = tasks.iter_mut().find(|task| task.id == id).ok_or("Task not found")?;
+    task.done = true;
 
-pub fn delete_task(tasks: &mut Vec<Task>, id: u32) {
-    tasks.retain(|task| task.id != id);
+    let next = match task.recurrence.take() {
+        Some(recurrence) => recurrence.next_date(task.date, today).map(|date| Task {
+            id: next_id,
+            date,
+            done: false,
+            recurrence: Some(recurrence),
+            ..task.clone()
+        }),
+        None => None,
+    };
+    Ok((task, next))
 }
@@ -0,0 +1,130 @@
+//! Ids for new tasks. Every list remembers the next id to hand out in a
+//! `<list file>.next_id` file next to it, so an id is never given to a second
+//! task, not even after the first one was deleted and the trash emptied.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::query::Query;
+use crate::store::{self, TaskStore};
+use crate::trash;
+
+pub fn counter_path(list_path: &str) -> String {
+    format!("{}.next_id", list_path)
+}
+
+/// The id after `id`, an error once the ids run out.
+pub fn following(id: u32) -> Result<u32, String> {
+    id.checked_add(1)
+        .ok_or(format!("Task id space exhausted, there is no id after {}", id))
+}
+
+/// The id remembered for the list at `path`, 1 for a list that never had one.
+fn read_counter(path: &str) -> Result<u32, String> {
+    let counter_path = counter_path(path);
+    if !Path::new(&counter_path).exists() {
+        return Ok(1);
+    }
+    let contents = fs::read_to_string(&counter_path)
+        .map_err(|e| format!("Error reading file {}: {}", counter_path, e))?;
+    contents
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| format!("Invalid next id in {}: {}", counter_path, contents.trim()))
+}
+
+/// Ids of the archived and deleted tasks of the list at `path`, they can come
+/// back so their ids stay taken.
+pub fn set_aside_ids(path: &str) -> Result<HashSet<u32>, String> {
+    let archived = store::query_archive(path, &Query::All)?;
+    let trashed = trash::load(&trash::trash_path(path))?;
+    Ok(archived
+        .iter()
+        .map(|task| task.id)
+        .chain(trashed.iter().map(|entry| entry.task.id))
+        .collect())
+}
+
+/// The id for the next new task of the list at `path`, given the id that
+/// follows the tasks in the list itself.
+pub fn next_id_after(path: &str, list_next: u32) -> Result<u32, String> {
+    let set_aside = match set_aside_ids(path)?.into_iter().max() {
+        Some(id) => following(id)?,
+        None => 1,
+    };
+    let next = read_counter(path)?.max(list_next).max(set_aside);
+    // the counter has to move past the id once it is handed out
+    following(next)?;
+    Ok(next)
+}
+
+/// The id for the next new task of the list at `path`. Call `hand_out` once it
+/// is used.
+pub fn next_id(store: &dyn TaskStore, path: &str) -> Result<u32, String> {
+    next_id_after(path, store.next_id()?)
+}
+
+/// Remember the ids the list at `path` and its archive and trash use now, so
+/// they stay taken once the tasks leave. Lists from before the counter was
+/// added have no counter file yet.
+pub fn seed(store: &dyn TaskStore, path: &str) -> Result<(), String> {
+    hand_out(path, next_id(store, path)? - 1)
+}
+
+/// Remember that every id up to `id` is taken in the list at `path`.
+pub fn hand_out(path: &str, id: u32) -> Result<(), String> {
+    if read_counter(path)? > id {
+        return Ok(());
+    }
+    let counter_path = counter_path(path);
+    fs::write(&counter_path, format!("{}\n", following(id)?))
+        .map_err(|e| format!("Error writing to file {}: {}", counter_path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    #[test]
+    fn ids_of_deleted_and_archived_tasks_stay_taken() {
+        let dir = TempDir::new();
+        let path = dir.path("tasks.csv");
+        let mut list = store::open_store(&path).unwrap();
+        list.write_all(&[testing::task(1, "kept", "2026-10-20")]).unwrap();
+        assert_eq!(next_id(list.as_ref(), &path), Ok(2));
+
+        store::open_archive(&path)
+            .unwrap()
+            .insert(&testing::task(4, "archived", "2026-10-01"))
+            .unwrap();
+        let deleted = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap().and_hms_opt(9, 0, 0).unwrap();
+        let trashed = vec![testing::task(6, "deleted", "2026-10-01")];
+        trash::add(&trash::trash_path(&path), trashed, deleted).unwrap();
+        assert_eq!(next_id(list.as_ref(), &path), Ok(7));
+
+        // the counter keeps them taken once they are gone for good
+        seed(list.as_ref(), &path).unwrap();
+        trash::empty(&trash::trash_path(&path), None).unwrap();
+        assert_eq!(next_id(list.as_ref(), &path), Ok(7));
+        hand_out(&path, 7).unwrap();
+        assert_eq!(next_id(list.as_ref(), &path), Ok(8));
+    }
+
+    #[test]
+    fn the_last_id_is_never_handed_out() {
+        let dir = TempDir::new();
+        let path = dir.path("tasks.csv");
+        let mut list = store::open_store(&path).unwrap();
+        list.write_all(&[testing::task(u32::MAX - 1, "almost", "2026-10-20")]).unwrap();
+        assert!(next_id(list.as_ref(), &path).is_err());
+        assert!(hand_out(&path, u32::MAX).is_err());
+        assert!(following(u32::MAX).is_err());
+
+        list.write_all(&[testing::task(u32::MAX, "last", "2026-10-20")]).unwrap();
+        assert!(list.next_id().is_err());
+    }
+}
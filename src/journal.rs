@@ -0,0 +1,589 @@
+//! Append-only journal of the changes made to a list, kept in a
+//! `<list file>.journal` file next to it with one json entry per line.
+//!
+//! Every command that changes tasks, the archive or the config adds an entry
+//! holding the tasks before and after the change. `kask undo` and `kask redo` put those
+//! back and add an entry of their own, nothing is ever removed from the file.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+
+use chrono::{Local, NaiveDateTime};
+use prettytable::Table;
+use serde::{Deserialize, Serialize};
+
+use crate::dates::DateSettings;
+use crate::query::Query;
+use crate::store::{self, TaskStore};
+use crate::{ids, trash, utils, KaskConfig, Task};
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+#[allow(clippy::large_enum_variant)]
+pub enum Change {
+    /// `before` is `None` for a created task and `after` for a deleted one
+    Task {
+        before: Option<Task>,
+        after: Option<Task>,
+    },
+    Config {
+        before: KaskConfig,
+        after: KaskConfig,
+    },
+    /// A task that went into the archive next to the list (`before` is
+    /// `None`) or came out of it (`after` is `None`)
+    Archive {
+        before: Option<Task>,
+        after: Option<Task>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum Action {
+    /// A command changed the list, `command` is how it was run
+    Change {
+        command: String,
+        changes: Vec<Change>,
+    },
+    /// The change with this id was undone
+    Undo { entry: u32 },
+    /// The change with this id was done again
+    Redo { entry: u32 },
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    pub id: u32,
+    pub time: NaiveDateTime,
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+pub fn journal_path(list_path: &str) -> String {
+    format!("{}.journal", list_path)
+}
+
+/// The command line kask was run with, without the program name.
+pub fn command_line() -> String {
+    std::env::args()
+        .skip(1)
+        .map(|arg| {
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                format!("\"{}\"", arg)
+            } else {
+                arg
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// The task changes that turn `before` into `after`, by id.
+pub fn diff(before: &[Task], after: &[Task]) -> Vec<Change> {
+    let mut changes: Vec<Change> = Vec::new();
+    for old in before.iter() {
+        match after.iter().find(|task| task.id == old.id) {
+            Some(new) if new == old => {}
+            new => changes.push(Change::Task {
+                before: Some(old.clone()),
+                after: new.cloned(),
+            }),
+        }
+    }
+    for new in after.iter() {
+        if !before.iter().any(|task| task.id == new.id) {
+            changes.push(Change::Task {
+                before: None,
+                after: Some(new.clone()),
+            });
+        }
+    }
+    changes
+}
+
+/// The archive changes that turn `before` into `after`, by id.
+pub fn archive_diff(before: &[Task], after: &[Task]) -> Vec<Change> {
+    diff(before, after)
+        .into_iter()
+        .map(|change| match change {
+            Change::Task { before, after } => Change::Archive { before, after },
+            change => change,
+        })
+        .collect()
+}
+
+/// A config change, `None` when the two configs are the same.
+pub fn config_change(before: &KaskConfig, after: &KaskConfig) -> Option<Change> {
+    if serde_json::to_value(before).ok() == serde_json::to_value(after).ok() {
+        return None;
+    }
+    Some(Change::Config {
+        before: before.clone(),
+        after: after.clone(),
+    })
+}
+
+pub fn read(path: &str) -> Result<Vec<Entry>, String> {
+    if !std::path::Path::new(path).exists() {
+        return Ok(Vec::new());
+    }
+    let contents = fs::read_to_string(path).map_err(|e| format!("Error reading file: {}", e))?;
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| format!("Error parsing line {} of {}: {}", index + 1, path, e))
+        })
+        .collect()
+}
+
+fn append(path: &str, entries: &[Entry], action: Action) -> Result<(), String> {
+    let entry = Entry {
+        id: entries.last().map(|entry| entry.id).unwrap_or(0) + 1,
+        time: Local::now().naive_local(),
+        action,
+    };
+    let line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .map_err(|e| format!("Error opening file: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Error writing to file: {}", e))
+}
+
+/// Add an entry for a command that made `changes`, commands that changed
+/// nothing are not recorded.
+pub fn record(path: &str, command: String, changes: Vec<Change>) -> Result<(), String> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    append(path, &read(path)?, Action::Change { command, changes })
+}
+
+/// Ids of the changes that are in effect, oldest first, and of the undone
+/// changes that can be done again, most recently undone last.
+fn state(entries: &[Entry]) -> (Vec<u32>, Vec<u32>) {
+    let mut applied: Vec<u32> = Vec::new();
+    let mut redoable: Vec<u32> = Vec::new();
+    for entry in entries.iter() {
+        match entry.action {
+            Action::Change { .. } => {
+                applied.push(entry.id);
+                // a new change starts a new line of history
+                redoable.clear();
+            }
+            Action::Undo { entry } => {
+                applied.retain(|id| *id != entry);
+                redoable.push(entry);
+            }
+            Action::Redo { entry } => {
+                redoable.retain(|id| *id != entry);
+                applied.push(entry);
+            }
+        }
+    }
+    (applied, redoable)
+}
+
+/// Turn `expected` into `target` in `tasks`, `false` when the task does not
+/// look like `expected` any more.
+fn replace(tasks: &mut Vec<Task>, expected: &Option<Task>, target: &Option<Task>) -> bool {
+    let id = target.as_ref().or(expected.as_ref()).map(|task| task.id).unwrap();
+    let position = tasks.iter().position(|task| task.id == id);
+    if position.map(|position| &tasks[position]) != expected.as_ref() {
+        return false;
+    }
+    match (position, target) {
+        (Some(position), Some(target)) => tasks[position] = target.clone(),
+        (Some(position), None) => {
+            tasks.remove(position);
+        }
+        (None, Some(target)) => tasks.push(target.clone()),
+        (None, None) => {}
+    }
+    true
+}
+
+/// Put back one side of `changes` to the list at `list_path`: the `before`
+/// side when undoing, the `after` side when redoing. The tasks, archive and
+/// config have to still look like the other side, otherwise they were changed
+/// outside of kask and nothing is touched.
+fn apply(
+    changes: &[Change],
+    undo: bool,
+    list_path: &str,
+    store: &mut dyn TaskStore,
+    config: Option<&KaskConfig>,
+) -> Result<(), String> {
+    let mut tasks = store.load()?;
+    let mut archived: Option<Vec<Task>> = None;
+    let mut new_config: Option<KaskConfig> = None;
+    let ordered: Vec<&Change> = if undo {
+        changes.iter().rev().collect()
+    } else {
+        changes.iter().collect()
+    };
+
+    for change in ordered {
+        match change {
+            Change::Task { before, after } => {
+                let (expected, target) = if undo { (after, before) } else { (before, after) };
+                if !replace(&mut tasks, expected, target) {
+                    let id = target.as_ref().or(expected.as_ref()).unwrap().id;
+                    return Err(format!("Task {} was changed since, it can not be restored", id));
+                }
+            }
+            Change::Archive { before, after } => {
+                let (expected, target) = if undo { (after, before) } else { (before, after) };
+                let archived = match &mut archived {
+                    Some(archived) => archived,
+                    None => archived.insert(store::query_archive(list_path, &Query::All)?),
+                };
+                if !replace(archived, expected, target) {
+                    let id = target.as_ref().or(expected.as_ref()).unwrap().id;
+                    return Err(format!(
+                        "Archived task {} was changed since, it can not be restored",
+                        id
+                    ));
+                }
+            }
+            Change::Config { before, after } => {
+                let (expected, target) = if undo { (after, before) } else { (before, after) };
+                let current = config.ok_or("No configuration file found")?;
+                if config_change(current, expected).is_some() {
+                    return Err(String::from(
+                        "The configuration was changed since, it can not be restored",
+                    ));
+                }
+                new_config = Some(target.clone());
+            }
+        }
+    }
+
+    if changes.iter().any(|change| matches!(change, Change::Task { .. })) {
+        tasks.sort_by_key(|task| task.id);
+        store.write_all(&tasks)?;
+    }
+    if let Some(mut archived) = archived {
+        archived.sort_by_key(|task| task.id);
+        store::open_archive(list_path)?.write_all(&archived)?;
+    }
+    if let Some(new_config) = new_config {
+        utils::write_config_to_file(new_config).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Undo the last `count` changes to the list at `list_path` that are in
+/// effect, or redo the last `count` undone ones. Returns the entries that were
+/// undone or redone.
+///
+/// Like `kask delete`, tasks that leave the list go to the trash unless they
+/// went back to the archive, and the ones that are back in the list leave the
+/// trash.
+pub fn undo_redo(
+    list_path: &str,
+    count: u32,
+    undo: bool,
+    store: &mut dyn TaskStore,
+) -> Result<Vec<u32>, String> {
+    // an undone create takes a task out of the list
+    ids::seed(store, list_path)?;
+    let before = store.load()?;
+    let done = undo_redo_entries(list_path, count, undo, store)?;
+
+    let after = store.load()?;
+    let archived = store::query_archive(list_path, &Query::All)?;
+    let trash_path = trash::trash_path(list_path);
+    let gone: Vec<Task> = before
+        .into_iter()
+        .filter(|task| !after.iter().any(|other| other.id == task.id))
+        .filter(|task| !archived.contains(task))
+        .collect();
+    trash::add(&trash_path, gone, Local::now().naive_local())?;
+    trash::remove_restored(&trash_path, &after)?;
+    Ok(done)
+}
+
+fn undo_redo_entries(
+    list_path: &str,
+    count: u32,
+    undo: bool,
+    store: &mut dyn TaskStore,
+) -> Result<Vec<u32>, String> {
+    let path = journal_path(list_path);
+    let path = path.as_str();
+    let mut entries = read(path)?;
+    let mut done: Vec<u32> = Vec::new();
+    for _ in 0..count {
+        let (applied, redoable) = state(&entries);
+        let next = if undo { applied.last() } else { redoable.last() };
+        let id = match next {
+            Some(id) => *id,
+            None => break,
+        };
+        let changes = match &entries.iter().find(|entry| entry.id == id).unwrap().action {
+            Action::Change { changes, .. } => changes.clone(),
+            _ => unreachable!(),
+        };
+        // read the config every time, an earlier entry may have just changed it
+        let config = if changes.iter().any(|change| matches!(change, Change::Config { .. })) {
+            utils::get_kask_config_file()?
+        } else {
+            None
+        };
+        apply(&changes, undo, list_path, store, config.as_ref())?;
+
+        let action = if undo {
+            Action::Undo { entry: id }
+        } else {
+            Action::Redo { entry: id }
+        };
+        append(path, &entries, action)?;
+        entries = read(path)?;
+        done.push(id);
+    }
+    Ok(done)
+}
+
+/// The command an entry was recorded for.
+pub fn command_of(entries: &[Entry], id: u32) -> String {
+    entries
+        .iter()
+        .find_map(|entry| match &entry.action {
+            Action::Change { command, .. } if entry.id == id => Some(command.clone()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+fn describe_changes(changes: &[Change]) -> String {
+    let mut created: Vec<String> = Vec::new();
+    let mut updated: Vec<String> = Vec::new();
+    let mut deleted: Vec<String> = Vec::new();
+    let mut archived: Vec<String> = Vec::new();
+    let mut unarchived: Vec<String> = Vec::new();
+    let mut config = false;
+    for change in changes {
+        match change {
+            Change::Task {
+                before: None,
+                after: Some(task),
+            } => created.push(task.id.to_string()),
+            Change::Task {
+                before: Some(task),
+                after: None,
+            } => deleted.push(task.id.to_string()),
+            Change::Task {
+                after: Some(task), ..
+            } => updated.push(task.id.to_string()),
+            Change::Task { .. } => {}
+            Change::Archive {
+                before: None,
+                after: Some(task),
+            } => archived.push(task.id.to_string()),
+            Change::Archive {
+                before: Some(task),
+                after: None,
+            } => unarchived.push(task.id.to_string()),
+            Change::Archive { .. } => {}
+            Change::Config { .. } => config = true,
+        }
+    }
+    // tasks that moved to or from the archive were not deleted or created
+    deleted.retain(|id| !archived.contains(id));
+    created.retain(|id| !unarchived.contains(id));
+
+    let mut parts: Vec<String> = Vec::new();
+    for (verb, ids) in [
+        ("created", created),
+        ("updated", updated),
+        ("deleted", deleted),
+        ("archived", archived),
+        ("unarchived", unarchived),
+    ] {
+        if !ids.is_empty() {
+            parts.push(format!("{} {}", verb, ids.join(", ")));
+        }
+    }
+    if config {
+        parts.push(String::from("config changed"));
+    }
+    parts.join("; ")
+}
+
+/// Show the last `count` entries of the journal, oldest first.
+pub fn print_history(path: &str, count: u32, settings: &DateSettings) -> Result<(), String> {
+    let entries = read(path)?;
+    let (applied, _) = state(&entries);
+    let undone: HashSet<u32> = entries
+        .iter()
+        .filter(|entry| matches!(entry.action, Action::Change { .. }))
+        .map(|entry| entry.id)
+        .filter(|id| !applied.contains(id))
+        .collect();
+
+    let mut table = Table::new();
+    table.set_titles(row!["#", "When", "Command", "Changes"]);
+    let skip = entries.len().saturating_sub(count as usize);
+    for entry in entries.iter().skip(skip) {
+        let when = format!(
+            "{} {}",
+            settings.format_date(&entry.time.date()),
+            settings.format_time(&Some(entry.time.time()))
+        );
+        let (command, changes) = match &entry.action {
+            Action::Change { command, changes } => {
+                let mut description = describe_changes(changes);
+                if undone.contains(&entry.id) {
+                    description.push_str(" (undone)");
+                }
+                (command.clone(), description)
+            }
+            Action::Undo { entry } => (String::from("undo"), format!("undid #{}", entry)),
+            Action::Redo { entry } => (String::from("redo"), format!("redid #{}", entry)),
+        };
+        table.add_row(row![entry.id, when, command, changes]);
+    }
+    table.printstd();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    /// Write `tasks` and `archived` as the new list and archive and record the
+    /// difference, the way a command does.
+    fn change(path: &str, store: &mut dyn TaskStore, tasks: &[Task], archived: &[Task]) {
+        let before = store.load().unwrap();
+        let archive_before = store::query_archive(path, &Query::All).unwrap();
+        store.write_all(tasks).unwrap();
+        store::open_archive(path).unwrap().write_all(archived).unwrap();
+        let mut changes = diff(&before, tasks);
+        changes.extend(archive_diff(&archive_before, archived));
+        record(&journal_path(path), String::from("test"), changes).unwrap();
+    }
+
+    fn trashed(path: &str) -> Vec<Task> {
+        trash::load(&trash::trash_path(path))
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.task)
+            .collect()
+    }
+
+    #[test]
+    fn undoes_and_redoes_updates_and_creates() {
+        let dir = TempDir::new();
+        let path = dir.path("tasks.csv");
+        let mut store = store::open_store(&path).unwrap();
+        let first = vec![testing::task(1, "first", "2026-10-20")];
+        store.write_all(&first).unwrap();
+        let mut second = first.clone();
+        second[0].name = String::from("renamed");
+        second.push(testing::task(2, "created", "2026-10-21"));
+        change(&path, store.as_mut(), &second, &[]);
+
+        assert_eq!(undo_redo(&path, 5, true, store.as_mut()), Ok(vec![1]));
+        assert_eq!(store.load().unwrap(), first);
+        // the undone create left the list like a deleted task, its id stays taken
+        assert_eq!(trashed(&path), second[1..].to_vec());
+        assert_eq!(ids::next_id(store.as_ref(), &path), Ok(3));
+        assert_eq!(undo_redo(&path, 1, true, store.as_mut()), Ok(vec![]));
+
+        assert_eq!(undo_redo(&path, 1, false, store.as_mut()), Ok(vec![1]));
+        assert_eq!(store.load().unwrap(), second);
+        assert!(trashed(&path).is_empty());
+        assert_eq!(undo_redo(&path, 1, false, store.as_mut()), Ok(vec![]));
+    }
+
+    #[test]
+    fn deleted_tasks_come_back_from_the_trash() {
+        let dir = TempDir::new();
+        let path = dir.path("tasks.csv");
+        let mut store = store::open_store(&path).unwrap();
+        let tasks = vec![
+            testing::task(1, "kept", "2026-10-20"),
+            testing::task(2, "deleted", "2026-10-21"),
+        ];
+        store.write_all(&tasks).unwrap();
+        trash::add(&trash::trash_path(&path), tasks[1..].to_vec(), Local::now().naive_local())
+            .unwrap();
+        change(&path, store.as_mut(), &tasks[..1], &[]);
+
+        undo_redo(&path, 1, true, store.as_mut()).unwrap();
+        assert_eq!(store.load().unwrap(), tasks);
+        assert!(trashed(&path).is_empty());
+
+        undo_redo(&path, 1, false, store.as_mut()).unwrap();
+        assert_eq!(store.load().unwrap(), tasks[..1].to_vec());
+        assert_eq!(trashed(&path), tasks[1..].to_vec());
+    }
+
+    #[test]
+    fn archived_tasks_go_back_to_the_archive_not_the_trash() {
+        let dir = TempDir::new();
+        let path = dir.path("tasks.csv");
+        let mut store = store::open_store(&path).unwrap();
+        let mut tasks = vec![
+            testing::task(1, "done", "2026-10-01"),
+            testing::task(2, "open", "2026-10-21"),
+        ];
+        tasks[0].done = true;
+        store.write_all(&tasks).unwrap();
+        change(&path, store.as_mut(), &tasks[1..], &tasks[..1]);
+
+        undo_redo(&path, 1, true, store.as_mut()).unwrap();
+        assert_eq!(store.load().unwrap(), tasks);
+        assert!(store::query_archive(&path, &Query::All).unwrap().is_empty());
+        assert!(trashed(&path).is_empty());
+
+        undo_redo(&path, 1, false, store.as_mut()).unwrap();
+        assert_eq!(store.load().unwrap(), tasks[1..].to_vec());
+        assert_eq!(store::query_archive(&path, &Query::All).unwrap(), tasks[..1].to_vec());
+        assert!(trashed(&path).is_empty());
+    }
+
+    #[test]
+    fn tasks_changed_since_are_left_alone() {
+        let dir = TempDir::new();
+        let path = dir.path("tasks.csv");
+        let mut store = store::open_store(&path).unwrap();
+        let task = testing::task(1, "first", "2026-10-20");
+        store.write_all(std::slice::from_ref(&task)).unwrap();
+        let mut renamed = task.clone();
+        renamed.name = String::from("renamed");
+        change(&path, store.as_mut(), std::slice::from_ref(&renamed), &[]);
+
+        // edited by hand after the change was recorded
+        let mut edited = renamed.clone();
+        edited.name = String::from("edited");
+        store.write_all(std::slice::from_ref(&edited)).unwrap();
+        assert!(undo_redo(&path, 1, true, store.as_mut()).is_err());
+        assert_eq!(store.load().unwrap(), vec![edited]);
+        let (applied, _) = state(&read(&journal_path(&path)).unwrap());
+        assert_eq!(applied, vec![1]);
+    }
+
+    #[test]
+    fn a_new_change_drops_what_could_be_redone() {
+        let dir = TempDir::new();
+        let path = dir.path("tasks.csv");
+        let mut store = store::open_store(&path).unwrap();
+        let first = vec![testing::task(1, "first", "2026-10-20")];
+        change(&path, store.as_mut(), &first, &[]);
+        undo_redo(&path, 1, true, store.as_mut()).unwrap();
+        let other = vec![testing::task(3, "other", "2026-10-22")];
+        change(&path, store.as_mut(), &other, &[]);
+
+        assert_eq!(undo_redo(&path, 1, false, store.as_mut()), Ok(vec![]));
+        assert_eq!(store.load().unwrap(), other);
+    }
+}
@@ -1,7 +1,17 @@
-use chrono::{Datelike, Local, NaiveDate};
+use std::cmp::Ordering;
+
+use chrono::Local;
 use clap::ValueEnum;
-use prettytable::{Table, Row, Cell};
+use prettytable::{Cell, Row, Table};
+use serde::{Deserialize, Serialize};
 
+use crate::period::PeriodArgs;
+use crate::priority::Priority;
+use crate::query::{Field, Op, Query, Value};
+use crate::tags::TagFilterArgs;
+use crate::dates::DateSettings;
+use crate::status::Status;
+use crate::{dependencies, subtasks};
 use crate::Task;
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
@@ -11,124 +21,318 @@ pub enum ShowMode {
     Done,    // shows only done
 }
 
-// search_tasks(tasks, query, start_date, end_date, tags, count);
-pub fn search_tasks(tasks: Vec<Task>, query: String, start_date: Option<String>, end_date: Option<String>, tags: Option<Vec<String>>, count: u32) {
-    // if start date is not specified then use today's date
-    let start_date: NaiveDate = if start_date.is_none() {
-        let today = Local::now();
-        NaiveDate::from_ymd_opt(today.date_naive().year(), today.month(), today.day()).unwrap()
-    } else {
-        NaiveDate::parse_from_str(&start_date.unwrap(), "%m/%d/%y").unwrap()
-    };
+impl ShowMode {
+    /// The done state tasks need to have to be shown in this mode.
+    pub fn done_filter(&self) -> Option<bool> {
+        match self {
+            ShowMode::NotDone => Some(false),
+            ShowMode::All => None,
+            ShowMode::Done => Some(true),
+        }
+    }
+}
 
-    // if end date is not specified then search for task from start date until end of time.
-    let end_date: NaiveDate = if end_date.is_none() {
-        NaiveDate::from_ymd_opt(9999, 12, 31).unwrap()
+/// Build the filter for `kask search`, the store applies it before the fuzzy match.
+pub fn search_filter(
+    period: PeriodArgs,
+    tag_filter: TagFilterArgs,
+    where_query: Option<String>,
+    settings: &DateSettings,
+) -> Result<Query, String> {
+    let today = Local::now().date_naive();
+    // without a period the tasks from today on and the open ones that are
+    // overdue are searched
+    let period = if period.is_set() {
+        period.into_query(today, settings)?
     } else {
-        NaiveDate::parse_from_str(&end_date.unwrap(), "%m/%d/%y").unwrap()
+        Query::Compare(Field::Date, Op::GtEq, Value::Date(today))
+            .or(Query::Compare(Field::Done, Op::Eq, Value::Bool(false)))
     };
 
+    Ok(period
+        .and(tag_filter.into_query())
+        .and(Query::parse_where(where_query, settings)?))
+}
+
+/// Search the tasks that passed `filter` for names close to `query`.
+/// `all_tasks` is the whole list, it is used for the progress of parent tasks.
+#[allow(clippy::too_many_arguments)]
+pub fn search_tasks(
+    tasks: Vec<Task>,
+    all_tasks: &[Task],
+    query: String,
+    filter: &Query,
+    count: u32,
+    sort: &[SortKey],
+    reverse: bool,
+    settings: &DateSettings,
+) {
     let mut filtered_tasks = tasks
         .into_iter()
         .filter(|task| {
-            let task_date = NaiveDate::parse_from_str(&task.date, "%m/%d/%y").unwrap();
-            if task_date >= start_date && task_date <= end_date {
-                // check the normalized levenstein distance and if it is less than 0.5 then return true
-                let dist = strsim::normalized_levenshtein(&task.name, &query);
-                if dist > 0.25 || task.name.contains(&query) {
-                    return true;
-                }
-
-                return false;
-            }
-            return false;
+            // check the normalized levenstein distance and if it is less than 0.5 then return true
+            let dist = strsim::normalized_levenshtein(&task.name, &query);
+            dist > 0.25 || task.name.contains(&query)
         })
         .collect::<Vec<Task>>();
 
-    filtered_tasks.sort_by(|a, b| {
-        let a_title = &a.name;
-        let b_title = &b.name;
-        let a_distance = strsim::levenshtein(a_title, &query);
-        let b_distance = strsim::levenshtein(b_title, &query);
-        a_distance.cmp(&b_distance)
-    });
+    sort_tasks(&mut filtered_tasks, sort, reverse, Some(&query));
 
     // only print out the top ten results
     println!("Searching for tasks with query: {}", query);
-    println!("Start Date: {}", start_date.format("%m/%d/%y"));
-    println!("End Date: {}", end_date.format("%m/%d/%y"));
+    println!("Filter: {}", filter);
     let title = format!(
         "Top {} results",
         std::cmp::min(count, filtered_tasks.len() as u32)
     );
-    table_print_tasks(filtered_tasks.into_iter().take(count as usize).collect(), &title);
+    // results stay in relevance order, subtasks are not moved under their parent
+    table_print_tasks(
+        filtered_tasks.into_iter().take(count as usize).map(|task| (0, task)).collect(),
+        all_tasks,
+        &title,
+        &DEFAULT_COLUMNS,
+        settings,
+    );
 }
 
-pub fn list_tasks(tasks: Vec<Task>, today: bool, week: bool, month: bool, show_mode: ShowMode, count: u32, list_name: String) {
-    let today_value = chrono::Local::now().date_naive();
-    let mut tasks_to_show: Vec<Task> = Vec::new();
-    if show_mode == ShowMode::All {
-        tasks_to_show = tasks;
-    } else if show_mode == ShowMode::Done {
-        tasks_to_show = tasks.into_iter().filter(|task| task.done).collect();
-    } else if show_mode == ShowMode::NotDone {
-        tasks_to_show = tasks.into_iter().filter(|task| !task.done).collect();
-    }
+/// Build the filter for `kask list` from its period switches, show mode and
+/// `--where` query.
+pub fn list_filter(
+    period: PeriodArgs,
+    show_mode: ShowMode,
+    tag_filter: TagFilterArgs,
+    where_query: Option<String>,
+    settings: &DateSettings,
+) -> Result<Query, String> {
+    let filter = match show_mode.done_filter() {
+        Some(done) => Query::Compare(Field::Done, Op::Eq, Value::Bool(done)),
+        None => Query::All,
+    };
 
-    if today {
-        // filter out tasks that are not today
-        tasks_to_show = tasks_to_show
-            .into_iter()
-            .filter(|task| {
-                NaiveDate::parse_from_str(&task.date, "%m/%d/%y").unwrap() == today_value
-            })
-            .collect();
-    } else if week {
-        // filter out tasks that are not this week, starting from today and ending the
-        // sunday of the same week
-
-        let today_weekday = today_value.weekday().num_days_from_monday();
-        let sunday = today_value + chrono::Duration::days(6 - today_weekday as i64);
-        tasks_to_show = tasks_to_show
-            .into_iter()
-            .filter(|task| {
-                let task_date = NaiveDate::parse_from_str(&task.date, "%m/%d/%y").unwrap();
-                task_date >= today_value && task_date <= sunday
-            })
-            .collect();
-    } else if month {
-        let today_month = today_value.month();
-        tasks_to_show = tasks_to_show
-            .into_iter()
-            .filter(|task| {
-                let task_date = NaiveDate::parse_from_str(&task.date, "%m/%d/%y").unwrap();
-                task_date.month() == today_month
-            })
-            .collect();
-    }
+    Ok(filter
+        .and(period.into_query(Local::now().date_naive(), settings)?)
+        .and(tag_filter.into_query())
+        .and(Query::parse_where(where_query, settings)?))
+}
+
+/// Build the filter for `kask next`, the open tasks that pass the tag filter
+/// and the `--where` query. Blocked tasks are left out afterwards since that
+/// depends on other tasks.
+pub fn next_filter(
+    tag_filter: TagFilterArgs,
+    where_query: Option<String>,
+    settings: &DateSettings,
+) -> Result<Query, String> {
+    Ok(Query::Compare(Field::Done, Op::Eq, Value::Bool(false))
+        .and(tag_filter.into_query())
+        .and(Query::parse_where(where_query, settings)?))
+}
+
+/// Build the filter for `kask overdue`, the open tasks due today at the latest.
+/// The ones due later today are left out afterwards, see `Status::of`.
+pub fn overdue_filter(
+    tag_filter: TagFilterArgs,
+    where_query: Option<String>,
+    settings: &DateSettings,
+) -> Result<Query, String> {
+    let today = Local::now().date_naive();
+    Ok(Query::Compare(Field::Done, Op::Eq, Value::Bool(false))
+        .and(Query::Compare(Field::Date, Op::LtEq, Value::Date(today)))
+        .and(tag_filter.into_query())
+        .and(Query::parse_where(where_query, settings)?))
+}
+
+/// Keys tasks can be sorted by, the first one that differs decides.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    Id,
+    Name,
+    Date,
+    Time,
+    Done,
+    /// The first tag of the task, tasks without tags go last
+    Tag,
+    /// Most urgent first
+    Priority,
+    /// Closest to the search query first, only used by `kask search`
+    Relevance,
+}
+
+/// What `kask list` sorts by unless the config says otherwise.
+pub const DEFAULT_SORT: [SortKey; 2] = [SortKey::Date, SortKey::Time];
+
+/// What `kask search` sorts by unless the config says otherwise.
+pub const DEFAULT_SEARCH_SORT: [SortKey; 1] = [SortKey::Relevance];
+
+/// What `kask next` sorts by, the most urgent work first.
+pub const DEFAULT_NEXT_SORT: [SortKey; 3] = [SortKey::Priority, SortKey::Date, SortKey::Time];
+
+/// Columns a task table can show.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Column {
+    Id,
+    Name,
+    Date,
+    Time,
+    Done,
+    Tags,
+    Description,
+    Priority,
+    Repeats,
+    /// Ids of the tasks it depends on
+    Depends,
+    /// Overdue, due today, upcoming or done
+    Status,
+}
+
+pub const DEFAULT_COLUMNS: [Column; 8] = [
+    Column::Id,
+    Column::Name,
+    Column::Date,
+    Column::Time,
+    Column::Status,
+    Column::Repeats,
+    Column::Priority,
+    Column::Done,
+];
 
-    // sort tthe asks by date and then by time 
-    tasks_to_show.sort_by(|a, b| {
-        let a_date = NaiveDate::parse_from_str(&a.date, "%m/%d/%y").unwrap();
-        let b_date = NaiveDate::parse_from_str(&b.date, "%m/%d/%y").unwrap();
-        if a_date == b_date {
-            a.time.cmp(&b.time)
+/// Sort by each key in turn, `query` is what relevance is measured against.
+pub fn sort_tasks(tasks: &mut [Task], keys: &[SortKey], reverse: bool, query: Option<&str>) {
+    tasks.sort_by(|a, b| {
+        let ordering = keys
+            .iter()
+            .map(|key| match key {
+                SortKey::Id => a.id.cmp(&b.id),
+                SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                SortKey::Date => a.date.cmp(&b.date),
+                SortKey::Time => a.due_time().cmp(&b.due_time()),
+                SortKey::Done => a.done.cmp(&b.done),
+                SortKey::Tag => match (a.tags.first(), b.tags.first()) {
+                    (Some(a_tag), Some(b_tag)) => a_tag.to_lowercase().cmp(&b_tag.to_lowercase()),
+                    (a_tag, b_tag) => b_tag.is_some().cmp(&a_tag.is_some()),
+                },
+                SortKey::Priority => b.priority.cmp(&a.priority),
+                SortKey::Relevance => match query {
+                    Some(query) => strsim::levenshtein(&a.name, query)
+                        .cmp(&strsim::levenshtein(&b.name, query)),
+                    None => Ordering::Equal,
+                },
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal);
+        if reverse {
+            ordering.reverse()
         } else {
-            a_date.cmp(&b_date)
+            ordering
         }
     });
+}
+
+/// Show `tasks_to_show` with every subtask indented under its parent.
+/// `all_tasks` is the whole list, it is used for the progress of parent tasks.
+#[allow(clippy::too_many_arguments)]
+pub fn list_tasks(
+    mut tasks_to_show: Vec<Task>,
+    all_tasks: &[Task],
+    count: u32,
+    list_name: String,
+    sort: &[SortKey],
+    reverse: bool,
+    columns: &[Column],
+    settings: &DateSettings,
+) {
+    sort_tasks(&mut tasks_to_show, sort, reverse, None);
+
+    let rows = subtasks::nest(tasks_to_show).into_iter().take(count as usize).collect();
+    table_print_tasks(rows, all_tasks, &list_name, columns, settings);
+}
 
-    tasks_to_show = tasks_to_show.into_iter().take(count as usize).collect();
-    table_print_tasks(tasks_to_show, &list_name);
+pub fn format_ids(ids: &[u32]) -> String {
+    ids.iter().map(|id| id.to_string()).collect::<Vec<String>>().join(", ")
 }
 
-fn table_print_tasks(tasks: Vec<Task>, title: &str) {
+/// Print `rows` of (depth, task). Tasks with subtasks show how many of them are
+/// done after their name, tasks that wait for open tasks are marked and the
+/// status is coloured.
+fn table_print_tasks(
+    rows: Vec<(usize, Task)>,
+    all_tasks: &[Task],
+    title: &str,
+    columns: &[Column],
+    settings: &DateSettings,
+) {
+    let progress = subtasks::progress(all_tasks);
+    let now = Local::now().naive_local();
     let mut table = Table::new();
     println!("\n");
     println!("{}:", title);
-    table.set_titles(row!["ID", "Name", "Date", "Time", "Done"]);
-    for task in tasks {
-        table.add_row(row![task.id, task.name, task.date, task.time, task.done]);
+    table.set_titles(Row::new(
+        columns
+            .iter()
+            .map(|column| {
+                Cell::new(match column {
+                    Column::Id => "ID",
+                    Column::Name => "Name",
+                    Column::Date => "Date",
+                    Column::Time => "Time",
+                    Column::Done => "Done",
+                    Column::Tags => "Tags",
+                    Column::Description => "Description",
+                    Column::Priority => "Priority",
+                    Column::Repeats => "Repeats",
+                    Column::Depends => "Depends on",
+                    Column::Status => "Status",
+                })
+            })
+            .collect(),
+    ));
+    for (depth, task) in rows {
+        let status = Status::of(&task, now);
+        let cells = columns.iter().map(|column| match column {
+            Column::Id => task.id.to_string(),
+            Column::Name => {
+                let mut name = task.name.clone();
+                if depth > 0 {
+                    name = format!("{}└ {}", "  ".repeat(depth - 1), name);
+                }
+                if let Some((done, total)) = progress.get(&task.id) {
+                    name = format!("{} ({}/{})", name, done, total);
+                }
+                let blocked_by = dependencies::blocked_by(&task, all_tasks);
+                if !task.done && !blocked_by.is_empty() {
+                    name = format!("{} [blocked by {}]", name, format_ids(&blocked_by));
+                }
+                name
+            }
+            Column::Date => settings.format_date(&task.date),
+            Column::Time => settings.format_time(&task.time),
+            Column::Done => task.done.to_string(),
+            Column::Tags => task.tags.join(", "),
+            Column::Description => task.description.clone(),
+            Column::Priority => match task.priority {
+                Priority::None => String::new(),
+                priority => priority.to_string(),
+            },
+            Column::Repeats => task
+                .recurrence
+                .as_ref()
+                .map(|recurrence| recurrence.to_string())
+                .unwrap_or_default(),
+            Column::Depends => format_ids(&task.depends_on),
+            Column::Status => status.to_string(),
+        });
+        table.add_row(Row::new(
+            columns
+                .iter()
+                .zip(cells)
+                .map(|(column, cell)| match column {
+                    Column::Status => Cell::new(&cell).style_spec(status.style()),
+                    _ => Cell::new(&cell),
+                })
+                .collect(),
+        ));
     }
     table.printstd();
 }
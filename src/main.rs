@@ -1,32 +1,153 @@
 #[macro_use] extern crate prettytable;
+mod archive;
 mod create;
+mod csv;
+mod dates;
+mod dependencies;
+mod doctor;
 mod edit;
+mod ids;
+mod journal;
 mod list;
+mod period;
+mod priority;
+mod query;
+mod recurrence;
+mod status;
+mod store;
+mod subtasks;
+mod tags;
+#[cfg(test)]
+mod testing;
+mod trash;
 mod utils;
+mod view;
 
 use std::str::FromStr;
-use std::{collections::HashMap, path::PathBuf};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 
+use chrono::{NaiveDate, NaiveTime, Weekday};
 use clap::{Parser, Subcommand};
-use list::ShowMode;
+use list::{ShowMode, SortKey};
+use period::PeriodArgs;
+use priority::Priority;
+use recurrence::Recurrence;
+use dates::DateSettings;
+use dependencies::DependCommand;
+use status::Status;
+use store::Backend;
+use tags::{TagCommand, TagFilterArgs};
+use trash::TrashCommand;
+use view::{View, ViewCommand};
 use serde::{Deserialize, Serialize};
 
-const DEFAULT_TIME: &str = "11:59pm";
+/// Format of the dates stored in the task lists
+const DATE_FORMAT: &str = "%m/%d/%y";
+/// Format of the times stored in the task lists
+const TIME_FORMAT: &str = "%I:%M%P";
+/// Tasks created without a time are due at the end of their day
+const DEFAULT_TIME: NaiveTime = match NaiveTime::from_hms_opt(23, 59, 0) {
+    Some(time) => time,
+    None => panic!("invalid default time"),
+};
 const CONFIG_FILE_ENV_VAR: &str = "KASK_CONFIG_FILE";
 
 fn main() {
     let args: Args = Args::parse();
 
-    let config_option = utils::get_kask_config_file();
-    if let None = config_option {
+    if let TaskCommand::Init { force } = args.task_command {
+        if let Err(error) = utils::init_kask_config(force) {
+            println!("Error: {}", error);
+        }
         return;
+    }
+
+    let loaded_config = match utils::get_kask_config_file() {
+        Ok(loaded_config) => loaded_config,
+        Err(error) => {
+            println!("Error: {}", error);
+            return;
+        }
+    };
+
+    // a task file passed on the command line is enough to run without a
+    // config, otherwise this is the first run and a default config is made
+    let loaded_config = match loaded_config {
+        None if args.task_file.is_none() => {
+            println!("No configuration file found, creating one");
+            match utils::init_kask_config(false) {
+                Ok(config) => Some(config),
+                Err(error) => {
+                    println!("Error: {}", error);
+                    return;
+                }
+            }
+        }
+        loaded_config => loaded_config,
     };
 
-    let config = config_option.unwrap();
+    if let Err(error) = run(args, loaded_config) {
+        println!("Error: {}", error);
+    }
+}
+
+/// Pick the list this invocation works on: --task-file, then --list, then the
+/// current list from the config. Returns the list name and its path.
+fn select_list(args: &Args, config: Option<&KaskConfig>) -> Result<(String, String), String> {
+    if let Some(task_file) = &args.task_file {
+        return Ok((task_file.clone(), task_file.clone()));
+    }
+
+    let config = config.ok_or("No configuration file found, pass --task-file to use a list without one")?;
+    let list = args.list.as_ref().unwrap_or(&config.current_tasks_list);
+    let path = config
+        .tasks_lists_paths
+        .get(list)
+        .ok_or(format!("Task list {} does not exist", list))?;
+    Ok((list.clone(), path.clone()))
+}
+
+fn run(args: Args, loaded_config: Option<KaskConfig>) -> Result<(), String> {
+    let (current_list, current_list_path) = select_list(&args, loaded_config.as_ref())?;
+    let config = loaded_config.clone().unwrap_or_default();
+    // doctor has to read lists that no longer load, so it runs before the store is opened
+    if let TaskCommand::Doctor { fix } = args.task_command {
+        return doctor::doctor(&current_list_path, fix);
+    }
 
-    let current_list = &config.current_tasks_list;
-    let current_list_path = &config.tasks_lists_paths[current_list];
-    let mut tasks = utils::load_tasks_from_file(current_list_path);
+    let mut store = store::open_store(&current_list_path)?;
+    let settings = DateSettings::from_config(&config)?;
+
+    // commands that change the list or the config are recorded in its journal
+    let journal_path = journal::journal_path(&current_list_path);
+    let changes_tasks = matches!(
+        args.task_command,
+        TaskCommand::Create { .. }
+            | TaskCommand::Update { .. }
+            | TaskCommand::Delete { .. }
+            | TaskCommand::Complete { .. }
+            | TaskCommand::Tag { .. }
+            | TaskCommand::Depend { .. }
+            | TaskCommand::Import { .. }
+            | TaskCommand::Archive { .. }
+            | TaskCommand::Unarchive { .. }
+            | TaskCommand::Trash {
+                trash_command: TrashCommand::Restore { .. }
+            }
+    );
+    let changes_archive = matches!(
+        args.task_command,
+        TaskCommand::Archive { .. } | TaskCommand::Unarchive { .. }
+    );
+    let changes_config =
+        matches!(args.task_command, TaskCommand::Config { .. } | TaskCommand::View { .. });
+    let tasks_before = if changes_tasks { Some(store.load()?) } else { None };
+    let archive_before = if changes_archive {
+        Some(store::query_archive(&current_list_path, &query::Query::All)?)
+    } else {
+        None
+    };
 
     match args.task_command {
         TaskCommand::Create {
@@ -35,22 +156,143 @@ fn main() {
             date,
             time,
             tags,
+            priority,
+            repeat,
+            parent,
+            depends_on,
         } => {
-            let id = tasks.last().map(|task| task.id + 1).unwrap_or(1);
-            let task = create::create_task(name, description, date, time, tags, id);
-            if task.is_none() {
-                return;
+            let mut tasks = store.load()?;
+            if let Some(parent) = parent {
+                if !tasks.iter().any(|task| task.id == parent) {
+                    return Err(format!("Parent task {} not found", parent));
+                }
+            }
+            let id = ids::next_id(store.as_ref(), &current_list_path)?;
+            let task = create::create_task(
+                name,
+                description,
+                date,
+                time,
+                tags,
+                priority,
+                repeat,
+                parent,
+                id,
+                &settings,
+            )?;
+            tasks.push(task);
+            dependencies::add_dependencies(&mut tasks, id, &depends_on)?;
+            let task = tasks.last().unwrap();
+            store.insert(task)?;
+            ids::hand_out(&current_list_path, id)?;
+            println!(
+                "Task {} created, due {}",
+                task.id,
+                dates::describe(&task.date, &task.time, &settings)
+            );
+            if let Some(recurrence) = &task.recurrence {
+                println!("Repeats {}", recurrence);
             }
-            utils::append_task_to_file(task.unwrap(), current_list_path);
         }
         TaskCommand::List {
-            today,
-            week,
-            month,
+            period,
             show_mode,
             count,
+            tag_filter,
+            where_query,
+            sort,
+            reverse,
+            blocked,
+            archived,
+        } => {
+            let overdue = period.overdue;
+            let filter = list::list_filter(period, show_mode, tag_filter, where_query, &settings)?;
+            let mut tasks = store.query(&filter)?;
+            let all_tasks = store::load_related(store.as_ref(), &tasks)?;
+            if overdue {
+                let now = chrono::Local::now().naive_local();
+                tasks.retain(|task| Status::of(task, now) == Status::Overdue);
+            }
+            if !blocked {
+                tasks.retain(|task| !dependencies::is_blocked(task, &all_tasks));
+            }
+            let sort = if sort.is_empty() { &config.sort } else { &sort };
+            list::list_tasks(
+                tasks,
+                &all_tasks,
+                count,
+                current_list.clone(),
+                sort,
+                reverse,
+                &list::DEFAULT_COLUMNS,
+                &settings,
+            );
+            if archived {
+                let archived_tasks = store::query_archive(&current_list_path, &filter)?;
+                let all_archived = if archived_tasks.is_empty() {
+                    Vec::new()
+                } else {
+                    let archive = store::open_archive(&current_list_path)?;
+                    store::load_related(archive.as_ref(), &archived_tasks)?
+                };
+                list::list_tasks(
+                    archived_tasks,
+                    &all_archived,
+                    count,
+                    format!("{} archive", current_list),
+                    sort,
+                    reverse,
+                    &list::DEFAULT_COLUMNS,
+                    &settings,
+                );
+            }
+        }
+        TaskCommand::Next {
+            count,
+            tag_filter,
+            where_query,
+            sort,
         } => {
-            list::list_tasks(tasks, today, week, month, show_mode, count, current_list.clone());
+            let filter = list::next_filter(tag_filter, where_query, &settings)?;
+            let mut tasks = store.query(&filter)?;
+            let all_tasks = store::load_related(store.as_ref(), &tasks)?;
+            tasks.retain(|task| {
+                !dependencies::is_blocked(task, &all_tasks)
+                    && !subtasks::has_open_subtasks(&all_tasks, task.id)
+            });
+            list::list_tasks(
+                tasks,
+                &all_tasks,
+                count,
+                format!("Next in {}", current_list),
+                if sort.is_empty() { &list::DEFAULT_NEXT_SORT } else { &sort },
+                false,
+                &list::DEFAULT_COLUMNS,
+                &settings,
+            );
+        }
+        TaskCommand::Overdue {
+            count,
+            tag_filter,
+            where_query,
+            sort,
+        } => {
+            let filter = list::overdue_filter(tag_filter, where_query, &settings)?;
+            let now = chrono::Local::now().naive_local();
+            let mut tasks = store.query(&filter)?;
+            tasks.retain(|task| Status::of(task, now) == Status::Overdue);
+            let all_tasks = store::load_related(store.as_ref(), &tasks)?;
+            let sort = if sort.is_empty() { &config.sort } else { &sort };
+            list::list_tasks(
+                tasks,
+                &all_tasks,
+                count,
+                format!("Overdue in {}", current_list),
+                sort,
+                false,
+                &list::DEFAULT_COLUMNS,
+                &settings,
+            );
         }
         TaskCommand::Update {
             id,
@@ -60,104 +302,612 @@ fn main() {
             time,
             done,
             tags,
+            priority,
+            repeat,
+            parent,
         } => {
-            if let Err(error) =
-                edit::edit_task(&mut tasks, id, name, description, date, time, done, tags)
-            {
-                println!("Error: {}", error);
-                return;
+            let mut tasks = store.load()?;
+            let due_changed = date.is_some() || time.is_some();
+            if let Some(parent) = parent {
+                subtasks::set_parent(&mut tasks, id, Some(parent).filter(|parent| *parent != 0))?;
             }
-            utils::write_tasks_to_file(current_list_path, tasks);
+            edit::edit_task(
+                &mut tasks,
+                id,
+                name,
+                description,
+                date,
+                time,
+                done,
+                tags,
+                priority,
+                repeat,
+                &settings,
+            )?;
+            let task = tasks.iter().find(|task| task.id == id).unwrap();
+            store.update(task)?;
             println!("Task updated successfully");
+            if due_changed {
+                println!("Now due {}", dates::describe(&task.date, &task.time, &settings));
+            }
         }
-        TaskCommand::Delete { id } => {
-            let new_tasks: Vec<Task> = tasks.into_iter().filter(|task| task.id != id).collect();
-            utils::write_tasks_to_file(current_list_path, new_tasks);
-            println!("Task deleted successfully");
+        TaskCommand::Delete {
+            id,
+            cascade,
+            orphan,
+            yes,
+        } => {
+            ids::seed(store.as_ref(), &current_list_path)?;
+            let mut tasks = store.load()?;
+            let children = subtasks::children_of(&tasks, id);
+            if !children.is_empty() && !cascade && !orphan {
+                return Err(format!(
+                    "Task {} has {} subtasks, use --cascade to delete them as well or --orphan \
+                     to keep them",
+                    id,
+                    children.len()
+                ));
+            }
+            let original = tasks.clone();
+            let removed = subtasks::remove_task(&mut tasks, id, cascade)?;
+            let task = original.iter().find(|task| task.id == id).unwrap();
+            let mut question = format!("Delete task {} ({})", id, task.name);
+            if removed.len() > 1 {
+                question.push_str(&format!(" and its {} subtasks", removed.len() - 1));
+            }
+            if !yes && !utils::confirm(&format!("{}?", question))? {
+                println!("Nothing was deleted");
+                return Ok(());
+            }
+            let trashed: Vec<Task> = original
+                .into_iter()
+                .filter(|task| removed.contains(&task.id))
+                .collect();
+            let deleted = chrono::Local::now().naive_local();
+            trash::add(&trash::trash_path(&current_list_path), trashed, deleted)?;
+            // tasks that waited on a deleted task stop waiting, orphans get a new parent
+            let dependents = dependencies::forget(&mut tasks, &removed);
+            let mut changed = dependents.clone();
+            if !cascade {
+                changed.extend(children.iter());
+            }
+            for removed_id in removed.iter() {
+                store.delete(*removed_id)?;
+            }
+            for task in tasks.iter().filter(|task| changed.contains(&task.id)) {
+                store.update(task)?;
+            }
+
+            println!("Task moved to the trash, `kask trash restore {}` brings it back", id);
+            if cascade && removed.len() > 1 {
+                println!("{} subtasks deleted as well", removed.len() - 1);
+            } else if !children.is_empty() {
+                println!("{} subtasks moved up", children.len());
+            }
+            if !dependents.is_empty() {
+                println!("{} tasks no longer wait for the deleted tasks", dependents.len());
+            }
         }
-        TaskCommand::Complete { id } => {
-            let new_tasks: Vec<Task> = tasks
+        TaskCommand::Complete { id, cascade } => {
+            let mut tasks = store.load()?;
+            let mut next_id = ids::next_id(store.as_ref(), &current_list_path)?;
+            let today = chrono::Local::now().date_naive();
+            let open: Vec<u32> = subtasks::descendants_of(&tasks, id)
                 .into_iter()
-                .map(|task| {
-                    if task.id == id {
-                        let mut new_task = task.clone();
-                        new_task.done = true;
-                        new_task
-                    } else {
-                        task.clone()
-                    }
-                })
+                .filter(|child| tasks.iter().any(|task| task.id == *child && !task.done))
                 .collect();
-            utils::write_tasks_to_file(current_list_path, new_tasks);
+            let ids = if cascade {
+                [vec![id], open.clone()].concat()
+            } else {
+                vec![id]
+            };
+
+            let mut created: Vec<Task> = Vec::new();
+            let mut ended: Vec<u32> = Vec::new();
+            for id in ids.iter() {
+                let repeats = tasks.iter().any(|task| task.id == *id && task.recurrence.is_some());
+                let (task, next) = edit::complete_task(&mut tasks, *id, next_id, today)?;
+                store.update(task)?;
+                match next {
+                    Some(next) => {
+                        ids::hand_out(&current_list_path, next_id)?;
+                        store.insert(&next)?;
+                        next_id = ids::following(next_id)?;
+                        created.push(next);
+                    }
+                    None if repeats => ended.push(*id),
+                    None => {}
+                }
+            }
             println!("Task completed successfully");
+            if cascade && !open.is_empty() {
+                println!("{} subtasks completed as well", open.len());
+            } else if !open.is_empty() {
+                println!(
+                    "Task {} still has {} open subtasks, use --cascade to complete them",
+                    id,
+                    open.len()
+                );
+            }
+            for next in created {
+                println!(
+                    "Task {} created for the next occurrence, due {}",
+                    next.id,
+                    dates::describe(&next.date, &next.time, &settings)
+                );
+            }
+            for id in ended {
+                println!("Task {} has no later occurrence, it does not repeat any more", id);
+            }
+            for ready in dependencies::unblocked_by(&tasks, &ids) {
+                println!("Task {} no longer waits for other tasks", ready);
+            }
         }
         TaskCommand::Search {
             query,
+            mut period,
             start_date,
             end_date,
-            tags,
+            tag_filter,
+            where_query,
             count,
+            sort,
+            reverse,
+            archived,
         } => {
-            list::search_tasks(tasks, query, start_date, end_date, tags, count);
+            period.from = period.from.or(start_date);
+            period.to = period.to.or(end_date);
+            let filter = list::search_filter(period, tag_filter, where_query, &settings)?;
+            let mut tasks = store.query(&filter)?;
+            let mut all_tasks = store::load_related(store.as_ref(), &tasks)?;
+            if archived {
+                let archived_tasks = store::query_archive(&current_list_path, &filter)?;
+                if !archived_tasks.is_empty() {
+                    let archive = store::open_archive(&current_list_path)?;
+                    all_tasks.extend(store::load_related(archive.as_ref(), &archived_tasks)?);
+                    tasks.extend(archived_tasks);
+                }
+            }
+            let sort = if sort.is_empty() { &config.search_sort } else { &sort };
+            list::search_tasks(
+                tasks,
+                &all_tasks,
+                query,
+                &filter,
+                count,
+                sort,
+                reverse,
+                &settings,
+            );
+        }
+        TaskCommand::Tag { tag_command } => {
+            let mut tasks = store.load()?;
+            // the ids given on the command line plus every task the query matches
+            let select = |mut ids: Vec<u32>, where_query: Option<String>| {
+                if let Some(where_query) = where_query {
+                    let query = query::Query::parse(&where_query, &settings)?;
+                    let matching = tasks.iter().filter(|task| query.matches(task));
+                    ids.extend(matching.map(|task| task.id));
+                }
+                Ok::<Vec<u32>, String>(ids)
+            };
+            let changed = match tag_command {
+                TagCommand::Add {
+                    tag,
+                    ids,
+                    where_query,
+                } => {
+                    let ids = select(ids, where_query)?;
+                    tags::add_tag(&mut tasks, &ids, &tag, &settings)?
+                }
+                TagCommand::Remove {
+                    tag,
+                    ids,
+                    where_query,
+                } => {
+                    let ids = select(ids, where_query)?;
+                    tags::remove_tag(&mut tasks, &ids, &tag, &settings)?
+                }
+                TagCommand::Rename { from, to } => {
+                    tags::rename_tag(&mut tasks, &from, &to, &settings)?
+                }
+                TagCommand::Merge { from, into } => {
+                    tags::merge_tags(&mut tasks, &from, &into, &settings)?
+                }
+                TagCommand::List { archived } => {
+                    if archived {
+                        tasks.extend(store::query_archive(&current_list_path, &query::Query::All)?);
+                    }
+                    tags::print_tag_counts(&tasks, &current_list);
+                    return Ok(());
+                }
+            };
+            if changed > 0 {
+                store.write_all(&tasks)?;
+            }
+            println!("{} tasks updated", changed);
+        }
+        TaskCommand::Depend { depend_command } => {
+            let mut tasks = store.load()?;
+            let id = match depend_command {
+                DependCommand::Add { id, on } => {
+                    dependencies::add_dependencies(&mut tasks, id, &on)?;
+                    id
+                }
+                DependCommand::Remove { id, on } => {
+                    dependencies::remove_dependencies(&mut tasks, id, &on)?;
+                    id
+                }
+            };
+            let task = tasks.iter().find(|task| task.id == id).unwrap();
+            store.update(task)?;
+            let blocked_by = dependencies::blocked_by(task, &tasks);
+            if blocked_by.is_empty() {
+                println!("Task {} does not wait for any open task", id);
+            } else {
+                println!("Task {} waits for {}", id, list::format_ids(&blocked_by));
+            }
+        }
+        TaskCommand::Export {
+            path,
+            format,
+            force,
+            where_query,
+        } => {
+            if !force && std::path::Path::new(&path).exists() {
+                return Err(format!("File {} already exists, use --force to overwrite it", path));
+            }
+            let format = format.unwrap_or(store::detect_backend(&path));
+            let tasks = store.query(&query::Query::parse_where(where_query, &settings)?)?;
+            store::open_store_with_backend(&path, format)?.write_all(&tasks)?;
+            println!("Exported {} tasks to {}", tasks.len(), path);
+        }
+        TaskCommand::Import { path, format } => {
+            if !std::path::Path::new(&path).exists() {
+                return Err(format!("File {} does not exist", path));
+            }
+            let format = format.unwrap_or(store::detect_backend(&path));
+            let imported = store::open_store_with_backend(&path, format)?.load()?;
+            let mut tasks = store.load()?;
+            let mut next_id = ids::next_id(store.as_ref(), &current_list_path)?;
+            // archived and deleted tasks keep their ids as well
+            let mut taken = ids::set_aside_ids(&current_list_path)?;
+            taken.extend(tasks.iter().map(|task| task.id));
+            let first_imported = tasks.len();
+            let mut renumbered: HashMap<u32, u32> = HashMap::new();
+            for mut task in imported {
+                // keep the id from the file unless it is already taken
+                if task.id == 0 || taken.contains(&task.id) {
+                    renumbered.insert(task.id, next_id);
+                    task.id = next_id;
+                }
+                next_id = next_id.max(ids::following(task.id)?);
+                taken.insert(task.id);
+                tasks.push(task);
+            }
+            // parents and dependencies follow the tasks that got a new id
+            for task in tasks[first_imported..].iter_mut() {
+                if let Some(parent) = task.parent.and_then(|parent| renumbered.get(&parent)) {
+                    task.parent = Some(*parent);
+                }
+                for dependency in task.depends_on.iter_mut() {
+                    *dependency = *renumbered.get(dependency).unwrap_or(dependency);
+                }
+            }
+            let count = tasks.len();
+            store.write_all(&tasks)?;
+            ids::hand_out(&current_list_path, next_id - 1)?;
+            println!("Imported tasks from {}, the list now has {} tasks", path, count);
         }
+        TaskCommand::View { name, view_command } => match view_command {
+            Some(ViewCommand::Save {
+                name,
+                where_query,
+                sort,
+                reverse,
+                columns,
+                count,
+                lists,
+                all_lists,
+                force,
+            }) => {
+                if loaded_config.is_none() {
+                    return Err(String::from("No configuration file found"));
+                }
+                if config.views.contains_key(&name) && !force {
+                    return Err(format!("View {} already exists, use --force to replace it", name));
+                }
+                let view = View {
+                    filter: where_query,
+                    sort,
+                    reverse,
+                    columns,
+                    count,
+                    lists,
+                    all_lists,
+                };
+                let known_lists: Vec<&String> = config.tasks_lists_paths.keys().collect();
+                view.validate(&name, &known_lists, &settings)?;
+                let mut new_config = config.clone();
+                new_config.views.insert(name.clone(), view);
+                utils::write_config_to_file(new_config).map_err(|e| e.to_string())?;
+                println!("View {} saved", name);
+            }
+            Some(ViewCommand::List {}) => view::print_views(config.views.iter()),
+            Some(ViewCommand::Remove { name }) => {
+                if loaded_config.is_none() || !config.views.contains_key(&name) {
+                    return Err(format!("View {} does not exist", name));
+                }
+                let mut new_config = config.clone();
+                new_config.views.remove(&name);
+                utils::write_config_to_file(new_config).map_err(|e| e.to_string())?;
+                println!("View {} removed", name);
+            }
+            None => {
+                // clap asks for a name when there is no subcommand
+                let name = name.unwrap_or_default();
+                let view = config
+                    .views
+                    .get(&name)
+                    .ok_or(format!("View {} does not exist", name))?;
+                let lists: Vec<(String, String)> = if view.all_lists {
+                    let mut lists: Vec<(String, String)> =
+                        config.tasks_lists_paths.clone().into_iter().collect();
+                    lists.sort();
+                    lists
+                } else if view.lists.is_empty() {
+                    vec![(current_list, current_list_path.clone())]
+                } else {
+                    view.lists
+                        .iter()
+                        .map(|list| match config.tasks_lists_paths.get(list) {
+                            Some(path) => Ok((list.clone(), path.clone())),
+                            None => Err(format!("Task list {} does not exist", list)),
+                        })
+                        .collect::<Result<_, String>>()?
+                };
+                view::show_view(&name, view, &lists, &config.sort, &settings)?;
+            }
+        },
+        TaskCommand::Archive { older_than } => {
+            ids::seed(store.as_ref(), &current_list_path)?;
+            let mut tasks = store.load()?;
+            let today = chrono::Local::now().date_naive();
+            let archived = archive::take_archivable(&mut tasks, older_than, today)?;
+            if archived.is_empty() {
+                println!("No done tasks to archive");
+            } else {
+                let mut archive = store::open_archive(&current_list_path)?;
+                for task in archived.iter() {
+                    archive.insert(task)?;
+                }
+                store.write_all(&tasks)?;
+                println!(
+                    "Archived {} tasks to {}",
+                    archived.len(),
+                    store::archive_path(&current_list_path)
+                );
+            }
+        }
+        TaskCommand::Unarchive { id } => {
+            let task = store::query_archive(&current_list_path, &query::Query::All)?
+                .into_iter()
+                .find(|task| task.id == id)
+                .ok_or(format!("Task {} is not in the archive", id))?;
+            let mut tasks = store.load()?;
+            let next_id = ids::next_id(store.as_ref(), &current_list_path)?;
+            let new_id = archive::restore(&mut tasks, task, next_id);
+            store.insert(tasks.last().unwrap())?;
+            ids::hand_out(&current_list_path, new_id)?;
+            store::open_archive(&current_list_path)?.delete(id)?;
+            if new_id == id {
+                println!("Task {} restored", id);
+            } else {
+                println!("Task {} restored as task {}, its id was taken", id, new_id);
+            }
+        }
+        TaskCommand::Trash { trash_command } => {
+            let trash_path = trash::trash_path(&current_list_path);
+            match trash_command {
+                TrashCommand::List {} => {
+                    let title = format!("Trash of {}", current_list);
+                    trash::print_trash(&trash::load(&trash_path)?, &title, &settings);
+                }
+                TrashCommand::Restore { id } => {
+                    let mut tasks = store.load()?;
+                    let next_id = ids::next_id(store.as_ref(), &current_list_path)?;
+                    let task = trash::take(&trash_path, id)?;
+                    let new_id = archive::restore(&mut tasks, task, next_id);
+                    store.insert(tasks.last().unwrap())?;
+                    ids::hand_out(&current_list_path, new_id)?;
+                    if new_id == id {
+                        println!("Task {} restored", id);
+                    } else {
+                        println!("Task {} restored as task {}, its id was taken", id, new_id);
+                    }
+                }
+                TrashCommand::Empty { older_than, yes } => {
+                    ids::seed(store.as_ref(), &current_list_path)?;
+                    let now = chrono::Local::now().naive_local();
+                    let before = match older_than {
+                        Some(age) => Some(trash::age_cutoff(&age, now)?),
+                        None => None,
+                    };
+                    let count = trash::load(&trash_path)?
+                        .iter()
+                        .filter(|entry| before.is_none_or(|before| entry.deleted < before))
+                        .count();
+                    if count == 0 {
+                        println!("Nothing to remove from the trash");
+                    } else if yes
+                        || utils::confirm(&format!("Remove {} tasks for good?", count))?
+                    {
+                        let removed = trash::empty(&trash_path, before)?;
+                        println!("Removed {} tasks from the trash", removed);
+                    } else {
+                        println!("Nothing was removed");
+                    }
+                }
+            }
+        }
+        TaskCommand::Undo { count } | TaskCommand::Redo { count } => {
+            let undo = matches!(args.task_command, TaskCommand::Undo { .. });
+            let done = journal::undo_redo(&current_list_path, count, undo, store.as_mut())?;
+            if done.is_empty() {
+                println!("Nothing to {}", if undo { "undo" } else { "redo" });
+            }
+            let entries = journal::read(&journal_path)?;
+            for id in done {
+                let verb = if undo { "Undid" } else { "Redid" };
+                println!("{} #{}: kask {}", verb, id, journal::command_of(&entries, id));
+            }
+        }
+        TaskCommand::History { count } => {
+            journal::print_history(&journal_path, count, &settings)?;
+        }
+        TaskCommand::Doctor { .. } | TaskCommand::Init { .. } => unreachable!(),
         TaskCommand::Config { config_command } => {
+            if loaded_config.is_none() {
+                return Err(String::from("No configuration file found"));
+            }
             match config_command {
                 ConfigCommand::Set { list } => {
                     if !config.tasks_lists_paths.contains_key(&list) {
-                        println!("Error: Task list {} does not exist", list);
-                        return;
+                        return Err(format!("Task list {} does not exist", list));
                     }
                     let mut new_config = config.clone();
                     new_config.current_tasks_list = list.clone();
-                    if let Err(error) = utils::write_config_to_file(new_config) {
-                        println!("Error: {}", error);
-                        return;
-                    };
+                    utils::write_config_to_file(new_config).map_err(|e| e.to_string())?;
                     println!("Current task list set to {}", list);
                 }
-                ConfigCommand::Add { list, path } => {
+                ConfigCommand::Add {
+                    list,
+                    path,
+                    backend,
+                } => {
                     if config.tasks_lists_paths.contains_key(&list) {
-                        println!("Error: Task list {} already exists", list);
-                        return;
+                        return Err(format!("Task list {} already exists", list));
                     }
                     // check if file exists and if it does not then create it and
                     // notify the user that the file was created
                     if !std::path::Path::new(&path).exists() {
-                        if let Err(error) = std::fs::File::create(&path) {
-                            println!("Error: {}", error);
-                            return;
-                        }
+                        let backend = backend.unwrap_or(store::detect_backend(&path));
+                        store::open_store_with_backend(&path, backend)?.write_all(&[])?;
                         println!("File {} created successfully", path);
+                    } else if let Some(backend) = backend {
+                        if store::detect_backend(&path) != backend {
+                            return Err(format!(
+                                "File {} is not a {:?} task list, use `kask config migrate` to convert it",
+                                path, backend
+                            ));
+                        }
                     }
 
                     let mut new_config = config.clone();
-                    let path = PathBuf::from(path).canonicalize().unwrap();
+                    let path = PathBuf::from(path).canonicalize().map_err(|e| e.to_string())?;
                     new_config.tasks_lists_paths.insert(
                         list.clone(),
                         path.to_str().unwrap().to_string(),
                     );
-                    if let Err(error) = utils::write_config_to_file(new_config) {
-                        println!("Error: {}", error);
-                        return;
-                    };
+                    utils::write_config_to_file(new_config).map_err(|e| e.to_string())?;
                     println!("Task list {} added successfully", list);
                 }
                 ConfigCommand::Remove { list } => {
                     if !config.tasks_lists_paths.contains_key(&list) {
-                        println!("Error: Task list {} does not exist", list);
-                        return;
+                        return Err(format!("Task list {} does not exist", list));
                     }
                     let mut new_config = config.clone();
                     new_config.tasks_lists_paths.remove(&list);
-                    if let Err(error) = utils::write_config_to_file(new_config) {
-                        println!("Error: {}", error);
-                        return;
-                    };
+                    utils::write_config_to_file(new_config).map_err(|e| e.to_string())?;
                     println!("Task list {} removed successfully", list);
                 }
+                ConfigCommand::Migrate {
+                    list,
+                    backend,
+                    path,
+                } => {
+                    let old_path = config
+                        .tasks_lists_paths
+                        .get(&list)
+                        .ok_or(format!("Task list {} does not exist", list))?;
+                    let new_path = match path {
+                        Some(path) => PathBuf::from(path),
+                        None => PathBuf::from(old_path).with_extension(backend.extension()),
+                    };
+                    if new_path.exists() {
+                        return Err(format!(
+                            "File {} already exists, pass --path to pick another location",
+                            new_path.display()
+                        ));
+                    }
+                    let count =
+                        store::migrate(old_path, new_path.to_str().unwrap(), backend)?;
+
+                    let mut new_config = config.clone();
+                    let new_path = new_path.canonicalize().map_err(|e| e.to_string())?;
+                    new_config.tasks_lists_paths.insert(
+                        list.clone(),
+                        new_path.to_str().unwrap().to_string(),
+                    );
+                    utils::write_config_to_file(new_config).map_err(|e| e.to_string())?;
+                    println!(
+                        "Migrated {} tasks from {} to {}, the old file was left in place",
+                        count,
+                        old_path,
+                        new_path.display()
+                    );
+                }
+                ConfigCommand::Format {
+                    date,
+                    time,
+                    week_start,
+                    locale,
+                } => {
+                    let mut new_config = config.clone();
+                    if let Some(date) = date {
+                        new_config.date_format = date;
+                    }
+                    if let Some(time) = time {
+                        new_config.time_format = time;
+                    }
+                    if let Some(week_start) = week_start {
+                        new_config.week_start = dates::parse_weekday(&week_start.to_lowercase())
+                            .ok_or(format!("Invalid week start: {}", week_start))?;
+                    }
+                    if let Some(locale) = locale {
+                        new_config.locale = if locale.is_empty() { None } else { Some(locale) };
+                    }
+                    let settings = DateSettings::from_config(&new_config)?;
+                    utils::write_config_to_file(new_config).map_err(|e| e.to_string())?;
+
+                    let now = chrono::Local::now().naive_local();
+                    println!(
+                        "Dates are now shown as {}",
+                        dates::describe(&now.date(), &Some(now.time()), &settings)
+                    );
+                    println!("Weeks start on {}", settings.week_start);
+                }
+                ConfigCommand::Sort { keys, search } => {
+                    let mut new_config = config.clone();
+                    if search {
+                        new_config.search_sort = keys;
+                    } else {
+                        new_config.sort = keys;
+                    }
+                    utils::write_config_to_file(new_config).map_err(|e| e.to_string())?;
+                    println!("Default sort order updated");
+                }
                 ConfigCommand::Info {} => {
                     println!("Configuration File: {}", utils::get_config_file_path().unwrap());
                     println!("Current task list: {}", config.current_tasks_list);
+                    println!("Date format: {}", config.date_format);
+                    println!("Time format: {}", config.time_format);
+                    println!("Week start: {}", config.week_start);
+                    if let Some(locale) = &config.locale {
+                        println!("Locale: {}", locale);
+                    }
+                    println!("List sort: {}", view::value_names(&config.sort));
+                    println!("Search sort: {}", view::value_names(&config.search_sort));
                     println!("Task Lists:");
                     for (list, path) in config.tasks_lists_paths.iter() {
                         println!("\t{}: {}", list, path);
@@ -166,12 +916,85 @@ fn main() {
             }
         }
     }
+
+    let mut changes = match tasks_before {
+        Some(before) => journal::diff(&before, &store.load()?),
+        None => Vec::new(),
+    };
+    if let Some(before) = archive_before {
+        let after = store::query_archive(&current_list_path, &query::Query::All)?;
+        changes.extend(journal::archive_diff(&before, &after));
+    }
+    if changes_config {
+        if let (Some(before), Some(after)) = (&loaded_config, utils::get_kask_config_file()?) {
+            changes.extend(journal::config_change(before, &after));
+        }
+    }
+    journal::record(&journal_path, journal::command_line(), changes)?;
+
+    Ok(())
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 struct KaskConfig {
     current_tasks_list: String,
     tasks_lists_paths: HashMap<String, String>,
+    /// strftime format used to read and show dates, the lists are always stored as mm/dd/yy
+    #[serde(default = "default_date_format")]
+    date_format: String,
+    /// strftime format used to read and show times
+    #[serde(default = "default_time_format")]
+    time_format: String,
+    #[serde(default = "default_week_start")]
+    week_start: Weekday,
+    /// Locale for month and day names, for example de_DE
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    locale: Option<String>,
+    /// Default sort order of `kask list` and of views saved without one
+    #[serde(default = "default_sort")]
+    sort: Vec<SortKey>,
+    /// Default sort order of `kask search`
+    #[serde(default = "default_search_sort")]
+    search_sort: Vec<SortKey>,
+    /// Saved views, see `kask view`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    views: BTreeMap<String, View>,
+}
+
+impl Default for KaskConfig {
+    fn default() -> KaskConfig {
+        KaskConfig {
+            current_tasks_list: String::new(),
+            tasks_lists_paths: HashMap::new(),
+            date_format: default_date_format(),
+            time_format: default_time_format(),
+            week_start: default_week_start(),
+            locale: None,
+            sort: default_sort(),
+            search_sort: default_search_sort(),
+            views: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_date_format() -> String {
+    DATE_FORMAT.to_string()
+}
+
+fn default_time_format() -> String {
+    TIME_FORMAT.to_string()
+}
+
+fn default_week_start() -> Weekday {
+    Weekday::Mon
+}
+
+fn default_sort() -> Vec<SortKey> {
+    list::DEFAULT_SORT.to_vec()
+}
+
+fn default_search_sort() -> Vec<SortKey> {
+    list::DEFAULT_SEARCH_SORT.to_vec()
 }
 
 #[derive(Parser, Debug)]
@@ -180,8 +1003,12 @@ struct Args {
     #[command(subcommand)]
     task_command: TaskCommand,
     #[clap(short, long)]
-    /// Path to the task list file to use
+    /// Path to the task list file to use, overrides the current list for this
+    /// command and works without a config file
     task_file: Option<String>,
+    /// Name of a configured task list to use instead of the current one
+    #[clap(short, long, conflicts_with = "task_file")]
+    list: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -189,66 +1016,254 @@ enum TaskCommand {
     /// Create a new task and add it to the current list
     Create {
         name: String,
+        /// Due date, either mm/dd/yy, yyyy-mm-dd or an expression such as
+        /// today, tomorrow, fri, next week, +3d, in 2 weeks or eom
         date: String,
         #[clap(short = 'm', long)]
         description: Option<String>,
+        /// Due time, either hh:mm[am|pm], 9am, 17:30, noon or midnight
         #[clap(short, long)]
         time: Option<String>,
         #[clap(long)]
         tags: Option<Vec<String>>,
+        /// Priority, either none, low, medium, high, urgent or ! to !!!!. A
+        /// trailing ! to !!!! word in the name sets it as well
+        #[clap(short, long)]
+        priority: Option<String>,
+        /// Repeat the task, for example daily, weekly on mon,thu, monthly on
+        /// the 15th, monthly on the last fri or every 3 days after completion
+        #[clap(short, long)]
+        repeat: Option<String>,
+        /// Id of the task to create this one as a subtask of
+        #[clap(long)]
+        parent: Option<u32>,
+        /// Comma separated ids of the tasks that have to be done first
+        #[clap(long, value_delimiter = ',')]
+        depends_on: Vec<u32>,
     },
     /// List tasks from the current list. Tasks will be sorted by date and by time
     /// completed tasks will not be shown by default. Use the --show-mode option to
     /// change this behavior
     List {
-        /// Show tasks for today
-        #[clap(short, long, group = "list_group")]
-        today: bool,
-        /// Show tasks for the current week
-        #[clap(short, long, group = "list_group")]
-        week: bool,
-        /// Show tasks for the current month
-        #[clap(short, long, group = "list_group")]
-        month: bool,
+        #[command(flatten)]
+        period: PeriodArgs,
         /// Show mode
         #[clap(short, long, value_enum, default_value = "not-done")]
         show_mode: ShowMode,
         /// Number of tasks to display
         #[clap(short, long, default_value = "10")]
         count: u32,
+        #[command(flatten)]
+        tag_filter: TagFilterArgs,
+        /// Only show tasks that match a query such as
+        /// 'tag:work and due<+7d and not done and name~"deploy"'
+        #[clap(long = "where", value_name = "QUERY")]
+        where_query: Option<String>,
+        /// Comma separated keys to sort by, for example priority,date.
+        /// Defaults to date,time, see `kask config sort`
+        #[clap(long, value_enum, value_delimiter = ',')]
+        sort: Vec<SortKey>,
+        /// Reverse the sort order
+        #[clap(long)]
+        reverse: bool,
+        /// Also show the tasks that wait for open tasks, they are hidden by default
+        #[clap(long)]
+        blocked: bool,
+        /// Also show the archived tasks that match, they are done so this needs
+        /// --show-mode all or done
+        #[clap(long)]
+        archived: bool,
+    },
+    /// Show the open tasks that can be worked on now, the ones that do not wait
+    /// for other tasks or subtasks. Sorted by priority and date by default
+    Next {
+        /// Number of tasks to display
+        #[clap(short, long, default_value = "10")]
+        count: u32,
+        #[command(flatten)]
+        tag_filter: TagFilterArgs,
+        /// Only show tasks that match a query, see `kask list --where`
+        #[clap(long = "where", value_name = "QUERY")]
+        where_query: Option<String>,
+        /// Comma separated keys to sort by
+        #[clap(long, value_enum, value_delimiter = ',')]
+        sort: Vec<SortKey>,
+    },
+    /// Show the open tasks of the current list that are past their due date and
+    /// time, the same as `kask list --overdue`
+    Overdue {
+        /// Number of tasks to display
+        #[clap(short, long, default_value = "10")]
+        count: u32,
+        #[command(flatten)]
+        tag_filter: TagFilterArgs,
+        /// Only show tasks that match a query, see `kask list --where`
+        #[clap(long = "where", value_name = "QUERY")]
+        where_query: Option<String>,
+        /// Comma separated keys to sort by
+        #[clap(long, value_enum, value_delimiter = ',')]
+        sort: Vec<SortKey>,
     },
     /// Update a task from the current list by its id
     Update {
         id: u32,
         #[clap(short, long)]
         name: Option<String>,
+        /// New due date, accepts the same values as create
         #[clap(short, long)]
         date: Option<String>,
         #[clap(short = 'm', long)]
         description: Option<String>,
+        /// New time, pass an empty value to clear it
         #[clap(short, long)]
         time: Option<String>,
         #[clap(long)]
         tags: Option<Vec<String>>,
         #[clap(long)]
         done: Option<bool>,
+        /// New priority, none clears it
+        #[clap(short, long)]
+        priority: Option<String>,
+        /// New recurrence rule, pass an empty value to stop repeating
+        #[clap(short, long)]
+        repeat: Option<String>,
+        /// Move the task under another task, 0 moves it to the top level
+        #[clap(long)]
+        parent: Option<u32>,
+    },
+    /// Move a task from the current list to its trash, see `kask trash`
+    Delete {
+        id: u32,
+        /// Delete the subtasks of the task as well
+        #[clap(long, conflicts_with = "orphan")]
+        cascade: bool,
+        /// Keep the subtasks of the task, they move up to its parent
+        #[clap(long)]
+        orphan: bool,
+        /// Do not ask for confirmation
+        #[clap(short, long)]
+        yes: bool,
     },
-    /// Delete a task from the current list by its id
-    Delete { id: u32 },
     /// Mark a task as complete by its id
-    Complete { id: u32 },
-    /// Search for tasks in the current list
+    Complete {
+        id: u32,
+        /// Complete the subtasks of the task as well
+        #[clap(long)]
+        cascade: bool,
+    },
+    /// Search for tasks in the current list, the ones due from today on and the
+    /// overdue ones unless a period is given
     Search {
         query: String,
-        #[clap(short, long)]
+        #[command(flatten)]
+        period: PeriodArgs,
+        /// Short for --from
+        #[clap(short = 's', value_name = "DATE", conflicts_with = "from")]
         start_date: Option<String>,
-        #[clap(short, long)]
+        /// Short for --to
+        #[clap(short = 'e', value_name = "DATE", conflicts_with = "to")]
         end_date: Option<String>,
+        #[command(flatten)]
+        tag_filter: TagFilterArgs,
+        /// Only search the tasks that match a query such as
+        /// 'tag:work and due<+7d and not done and name~"deploy"'
+        #[clap(long = "where", value_name = "QUERY")]
+        where_query: Option<String>,
+        #[clap(short, long, default_value = "10")]
+        count: u32,
+        /// Comma separated keys to sort the matches by, they are sorted by how
+        /// close they are to the query (relevance) by default
+        #[clap(long, value_enum, value_delimiter = ',')]
+        sort: Vec<SortKey>,
+        /// Reverse the sort order
         #[clap(long)]
-        tags: Option<Vec<String>>,
+        reverse: bool,
+        /// Search the archived tasks as well
+        #[clap(long)]
+        archived: bool,
+    },
+    /// Manage the tags of the current list
+    Tag {
+        #[clap(subcommand)]
+        tag_command: TagCommand,
+    },
+    /// Manage which tasks have to wait for other tasks
+    Depend {
+        #[clap(subcommand)]
+        depend_command: DependCommand,
+    },
+    /// Write the tasks of the current list to a file
+    Export {
+        path: String,
+        /// Format of the file, picked from its extension by default
+        #[clap(short, long, value_enum)]
+        format: Option<Backend>,
+        /// Overwrite the file if it already exists
+        #[clap(long)]
+        force: bool,
+        /// Only export the tasks that match this query, see `kask list --where`
+        #[clap(long = "where", value_name = "QUERY")]
+        where_query: Option<String>,
+    },
+    /// Add the tasks from a file to the current list
+    Import {
+        path: String,
+        /// Format of the file, picked from its extension by default
+        #[clap(short, long, value_enum)]
+        format: Option<Backend>,
+    },
+    /// Show a saved view, or manage them with the subcommands
+    #[clap(args_conflicts_with_subcommands = true, arg_required_else_help = true)]
+    View {
+        /// Name of the view to show
+        name: Option<String>,
+        #[clap(subcommand)]
+        view_command: Option<ViewCommand>,
+    },
+    /// Move the done tasks of the current list to its archive, a file next to
+    /// the list such as tasks.archive.csv for tasks.csv
+    Archive {
+        /// Only archive the tasks due at least this many days ago
+        #[clap(long, value_name = "DAYS")]
+        older_than: Option<u32>,
+    },
+    /// Move a task from the archive back to the current list
+    Unarchive { id: u32 },
+    /// Show, restore or remove the deleted tasks of the current list
+    Trash {
+        #[clap(subcommand)]
+        trash_command: TrashCommand,
+    },
+    /// Undo the last changes made to the current list or the configuration
+    Undo {
+        /// Number of changes to undo
+        #[clap(default_value = "1")]
+        count: u32,
+    },
+    /// Redo changes that were undone
+    Redo {
+        /// Number of changes to redo
+        #[clap(default_value = "1")]
+        count: u32,
+    },
+    /// Show the changes recorded in the journal of the current list
+    History {
+        /// Number of entries to show
         #[clap(short, long, default_value = "10")]
         count: u32,
     },
+    /// Check the current list for rows that do not load and repair them
+    Doctor {
+        /// Rewrite the list with the repaired rows instead of only reporting them
+        #[clap(long)]
+        fix: bool,
+    },
+    /// Write a default configuration file and task list
+    Init {
+        /// Replace the configuration file if it already exists
+        #[clap(long)]
+        force: bool,
+    },
     /// Configuration commands
     Config {
         #[clap(subcommand)]
@@ -260,30 +1275,95 @@ enum TaskCommand {
 enum ConfigCommand {
     /// Set the current task list
     Set { list: String },
-    /// Add a new task list
-    Add { list: String, path: String },
+    /// Add a new task list. The storage backend is picked from the file
+    /// extension unless --backend is given
+    Add {
+        list: String,
+        path: String,
+        #[clap(long, value_enum)]
+        backend: Option<Backend>,
+    },
     /// Remove a task list
     Remove { list: String },
+    /// Copy a task list into another storage backend and point the list at it
+    Migrate {
+        list: String,
+        #[clap(long, value_enum)]
+        backend: Backend,
+        /// Where to write the new list, defaults to the old path with the
+        /// backend's extension
+        #[clap(long)]
+        path: Option<String>,
+    },
+    /// Set how dates and times are read and shown, the csv lists keep
+    /// storing them as mm/dd/yy and hh:mm[am|pm]
+    Format {
+        /// strftime date format, for example %d.%m.%Y
+        #[clap(long)]
+        date: Option<String>,
+        /// strftime time format, for example %H:%M
+        #[clap(long)]
+        time: Option<String>,
+        /// First day of the week, for example monday or sunday
+        #[clap(long)]
+        week_start: Option<String>,
+        /// Locale for month and day names, for example de_DE. Pass an empty
+        /// value to go back to english
+        #[clap(long)]
+        locale: Option<String>,
+    },
+    /// Set the default sort order of list, or of search with --search
+    Sort {
+        /// Comma separated keys, for example priority,date,time
+        #[clap(value_enum, value_delimiter = ',', required = true)]
+        keys: Vec<SortKey>,
+        #[clap(long)]
+        search: bool,
+    },
     /// Dispaly Configuration information
     Info {},
 }
 
-#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Task {
     pub id: u32,
     pub name: String,
-    pub date: String,
-    pub time: String,
+    #[serde(with = "utils::serde_date")]
+    pub date: NaiveDate,
+    #[serde(with = "utils::serde_time", default)]
+    pub time: Option<NaiveTime>,
+    #[serde(default)]
     pub description: String,
+    #[serde(default)]
     pub done: bool,
+    #[serde(default)]
     pub tags: Vec<String>,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<Recurrence>,
+    /// Id of the task this one is a subtask of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<u32>,
+    /// Ids of the tasks that have to be done before this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<u32>,
+}
+
+impl Task {
+    /// Time the task is due at, tasks without a time are due at the end of the day.
+    pub fn due_time(&self) -> NaiveTime {
+        self.time.unwrap_or(DEFAULT_TIME)
+    }
 }
 
+/// Parses a single line of the legacy task list format, where fields are joined
+/// by `", "` with no quoting. New files are read through `utils` instead.
 impl FromStr for Task {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Vec<&str> = s.split(",").collect();
+        let parts: Vec<&str> = s.split(',').map(|part| part.trim()).collect();
         if parts.len() != 7 {
             return Err(format!(
                 "Invalid number of parts in task string: expected 7, found {}",
@@ -293,13 +1373,14 @@ impl FromStr for Task {
 
         let id = parts[0].parse::<u32>().map_err(|e| e.to_string())?;
         let name = parts[1].to_string();
-        let date = parts[2].to_string();
-        let time = parts[3].to_string();
+        let date = utils::parse_stored_date(parts[2])?;
+        let time = utils::parse_stored_time(parts[3])?;
         let description = parts[4].to_string();
-        let done = parts[5].trim().parse::<bool>().map_err(|e| e.to_string())?;
+        let done = parts[5].parse::<bool>().map_err(|e| e.to_string())?;
         let tags = parts[6]
-            .split(";")
-            .map(|tag| tag.to_string())
+            .split(';')
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
             .collect::<Vec<String>>();
 
         Ok(Task {
@@ -310,6 +1391,10 @@ impl FromStr for Task {
             description,
             done,
             tags,
+            priority: Priority::None,
+            recurrence: None,
+            parent: None,
+            depends_on: Vec::new(),
         })
     }
 }
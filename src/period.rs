@@ -0,0 +1,265 @@
+//! Due date ranges for `kask list` and `kask search`. A period is a calendar
+//! day, week, month or year, optionally shifted by an offset such as
+//! `--week +1` for next week, narrowed further by `--from` and `--to`.
+
+use chrono::{Datelike, Days, Duration, Months, NaiveDate};
+use clap::Args;
+
+use crate::dates::{self, DateSettings};
+use crate::query::{Field, Op, Query, Value};
+
+/// Period switches shared by `kask list` and `kask search`.
+#[derive(Args, Debug, Clone, Default)]
+pub struct PeriodArgs {
+    /// Show tasks due today, or that many days from today such as -1 for yesterday
+    #[clap(
+        short,
+        long,
+        group = "period",
+        value_name = "OFFSET",
+        num_args = 0..=1,
+        default_missing_value = "0",
+        allow_negative_numbers = true
+    )]
+    pub today: Option<i32>,
+    /// Show tasks due this week, or that many weeks from it such as +1 for next week
+    #[clap(
+        short,
+        long,
+        group = "period",
+        value_name = "OFFSET",
+        num_args = 0..=1,
+        default_missing_value = "0",
+        allow_negative_numbers = true
+    )]
+    pub week: Option<i32>,
+    /// Show tasks due this month, or that many months from it
+    #[clap(
+        short,
+        long,
+        group = "period",
+        value_name = "OFFSET",
+        num_args = 0..=1,
+        default_missing_value = "0",
+        allow_negative_numbers = true
+    )]
+    pub month: Option<i32>,
+    /// Show tasks due this year, or that many years from it
+    #[clap(
+        short,
+        long,
+        group = "period",
+        value_name = "OFFSET",
+        num_args = 0..=1,
+        default_missing_value = "0",
+        allow_negative_numbers = true
+    )]
+    pub year: Option<i32>,
+    /// Show the open tasks that are past their due date and time
+    #[clap(long, group = "period")]
+    pub overdue: bool,
+    /// Show tasks due on or after this date, accepts the same values as create
+    #[clap(long, alias = "start-date", value_name = "DATE")]
+    pub from: Option<String>,
+    /// Show tasks due on or before this date
+    #[clap(long, alias = "end-date", value_name = "DATE")]
+    pub to: Option<String>,
+}
+
+impl PeriodArgs {
+    /// Whether any period switch or bound was given.
+    pub fn is_set(&self) -> bool {
+        self.today.is_some()
+            || self.week.is_some()
+            || self.month.is_some()
+            || self.year.is_some()
+            || self.overdue
+            || self.from.is_some()
+            || self.to.is_some()
+    }
+
+    /// The query the switches stand for, relative to `today`.
+    pub fn into_query(self, today: NaiveDate, settings: &DateSettings) -> Result<Query, String> {
+        let mut query = Query::All;
+        if let Some((first, last)) = self.calendar_range(today, settings)? {
+            query = query.and(date_range(first, last));
+        }
+        // the time of the tasks due today is checked afterwards, see `Status::of`
+        if self.overdue {
+            query = query
+                .and(Query::Compare(Field::Done, Op::Eq, Value::Bool(false)))
+                .and(Query::Compare(Field::Date, Op::LtEq, Value::Date(today)));
+        }
+        if let Some(from) = &self.from {
+            let from = dates::parse_date_relative(from, today, settings)?;
+            query = query.and(Query::Compare(Field::Date, Op::GtEq, Value::Date(from)));
+        }
+        if let Some(to) = &self.to {
+            let to = dates::parse_date_relative(to, today, settings)?;
+            query = query.and(Query::Compare(Field::Date, Op::LtEq, Value::Date(to)));
+        }
+        Ok(query)
+    }
+
+    /// First and last day of the day, week, month or year that was asked for.
+    fn calendar_range(
+        &self,
+        today: NaiveDate,
+        settings: &DateSettings,
+    ) -> Result<Option<(NaiveDate, NaiveDate)>, String> {
+        let out_of_range = || String::from("The period is out of the supported date range");
+        let range = if let Some(offset) = self.today {
+            let day = today
+                .checked_add_signed(Duration::days(offset as i64))
+                .ok_or_else(out_of_range)?;
+            (day, day)
+        } else if let Some(offset) = self.week {
+            let first = settings
+                .start_of_week(today)
+                .checked_add_signed(Duration::weeks(offset as i64))
+                .ok_or_else(out_of_range)?;
+            (first, first.checked_add_days(Days::new(6)).ok_or_else(out_of_range)?)
+        } else if let Some(offset) = self.month {
+            let first = add_months(today.with_day(1).unwrap(), offset).ok_or_else(out_of_range)?;
+            (first, dates::end_of_month(first).ok_or_else(out_of_range)?)
+        } else if let Some(offset) = self.year {
+            let year = today.year().checked_add(offset).ok_or_else(out_of_range)?;
+            let first = NaiveDate::from_ymd_opt(year, 1, 1).ok_or_else(out_of_range)?;
+            let last = NaiveDate::from_ymd_opt(year, 12, 31).ok_or_else(out_of_range)?;
+            (first, last)
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(range))
+    }
+}
+
+fn add_months(date: NaiveDate, months: i32) -> Option<NaiveDate> {
+    let offset = Months::new(months.unsigned_abs());
+    if months >= 0 {
+        date.checked_add_months(offset)
+    } else {
+        date.checked_sub_months(offset)
+    }
+}
+
+/// Tasks due between `first` and `last`, both included.
+fn date_range(first: NaiveDate, last: NaiveDate) -> Query {
+    let date = |op: Op, date: NaiveDate| Query::Compare(Field::Date, op, Value::Date(date));
+    date(Op::GtEq, first).and(date(Op::LtEq, last))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Weekday;
+
+    use super::*;
+    use crate::testing;
+
+    fn date(input: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(input, "%Y-%m-%d").unwrap()
+    }
+
+    /// The range of `period` on `today` as `first..last`.
+    fn range(period: PeriodArgs, today: &str, settings: &DateSettings) -> String {
+        let (first, last) = period.calendar_range(date(today), settings).unwrap().unwrap();
+        format!("{}..{}", first, last)
+    }
+
+    #[test]
+    fn months_belong_to_their_year() {
+        let settings = DateSettings::default();
+        let month = |offset| PeriodArgs {
+            month: Some(offset),
+            ..PeriodArgs::default()
+        };
+        assert_eq!(range(month(0), "2025-12-15", &settings), "2025-12-01..2025-12-31");
+        assert_eq!(range(month(1), "2025-12-15", &settings), "2026-01-01..2026-01-31");
+        assert_eq!(range(month(-12), "2026-12-15", &settings), "2025-12-01..2025-12-31");
+        assert_eq!(range(month(2), "2027-12-31", &settings), "2028-02-01..2028-02-29");
+
+        let query = month(0).into_query(date("2025-12-15"), &settings).unwrap();
+        assert!(query.matches(&testing::task(1, "this december", "2025-12-10")));
+        assert!(!query.matches(&testing::task(2, "next december", "2026-12-10")));
+    }
+
+    #[test]
+    fn weeks_start_on_the_configured_day() {
+        let monday = DateSettings::default();
+        let sunday = DateSettings {
+            week_start: Weekday::Sun,
+            ..DateSettings::default()
+        };
+        let week = |offset| PeriodArgs {
+            week: Some(offset),
+            ..PeriodArgs::default()
+        };
+        // wednesday 2025-12-31
+        assert_eq!(range(week(0), "2025-12-31", &monday), "2025-12-29..2026-01-04");
+        assert_eq!(range(week(0), "2025-12-31", &sunday), "2025-12-28..2026-01-03");
+        assert_eq!(range(week(1), "2025-12-31", &monday), "2026-01-05..2026-01-11");
+        assert_eq!(range(week(-1), "2026-01-01", &sunday), "2025-12-21..2025-12-27");
+
+        let query = week(0).into_query(date("2025-12-31"), &monday).unwrap();
+        assert!(query.matches(&testing::task(1, "monday", "2025-12-29")));
+        assert!(query.matches(&testing::task(2, "new year", "2026-01-01")));
+        assert!(!query.matches(&testing::task(3, "a year later", "2026-12-30")));
+    }
+
+    #[test]
+    fn days_and_years_cross_the_year_boundary() {
+        let settings = DateSettings::default();
+        let today = PeriodArgs {
+            today: Some(1),
+            ..PeriodArgs::default()
+        };
+        let year = |offset| PeriodArgs {
+            year: Some(offset),
+            ..PeriodArgs::default()
+        };
+        assert_eq!(range(today, "2025-12-31", &settings), "2026-01-01..2026-01-01");
+        assert_eq!(range(year(0), "2025-12-31", &settings), "2025-01-01..2025-12-31");
+        assert_eq!(range(year(1), "2025-12-31", &settings), "2026-01-01..2026-12-31");
+    }
+
+    #[test]
+    fn bounds_narrow_the_period() {
+        let settings = DateSettings::default();
+        let period = PeriodArgs {
+            month: Some(0),
+            from: Some(String::from("2025-12-20")),
+            ..PeriodArgs::default()
+        };
+        let query = period.into_query(date("2025-12-15"), &settings).unwrap();
+        assert!(!query.matches(&testing::task(1, "before", "2025-12-19")));
+        assert!(query.matches(&testing::task(2, "after", "2025-12-20")));
+        let query = PeriodArgs::default().into_query(date("2025-12-15"), &settings);
+        assert_eq!(query, Ok(Query::All));
+    }
+
+    #[test]
+    fn offsets_past_the_calendar_are_errors() {
+        let settings = DateSettings::default();
+        let today = date("2025-12-15");
+        for period in [
+            PeriodArgs {
+                today: Some(i32::MAX),
+                ..PeriodArgs::default()
+            },
+            PeriodArgs {
+                week: Some(i32::MIN),
+                ..PeriodArgs::default()
+            },
+            PeriodArgs {
+                month: Some(i32::MAX),
+                ..PeriodArgs::default()
+            },
+            PeriodArgs {
+                year: Some(i32::MAX),
+                ..PeriodArgs::default()
+            },
+        ] {
+            assert!(period.into_query(today, &settings).is_err());
+        }
+    }
+}
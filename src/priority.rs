@@ -0,0 +1,88 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// How urgent a task is, tasks are created without a priority.
+#[derive(
+    Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    /// Number stored in the sqlite lists, it keeps the order of the levels.
+    pub fn level(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_level(level: u8) -> Option<Priority> {
+        match level {
+            0 => Some(Priority::None),
+            1 => Some(Priority::Low),
+            2 => Some(Priority::Medium),
+            3 => Some(Priority::High),
+            4 => Some(Priority::Urgent),
+            _ => None,
+        }
+    }
+}
+
+/// Accepts the names, their first letter and the `!` to `!!!!` shorthand.
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "none" | "n" => Ok(Priority::None),
+            "low" | "l" | "!" => Ok(Priority::Low),
+            "medium" | "med" | "m" | "!!" => Ok(Priority::Medium),
+            "high" | "h" | "!!!" => Ok(Priority::High),
+            "urgent" | "u" | "!!!!" => Ok(Priority::Urgent),
+            _ => Err(format!(
+                "Invalid priority: {} (expected none, low, medium, high, urgent or ! to !!!!)",
+                s.trim()
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Priority::None => "none",
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Take a `!`, `!!`, `!!!` or `!!!!` word off the start or the end of a task
+/// name, so `kask create "call the bank !!" tomorrow` is a medium priority task.
+pub fn split_shorthand(name: &str) -> (String, Option<Priority>) {
+    let name = name.trim();
+    let is_shorthand =
+        |word: &str| !word.is_empty() && word.len() <= 4 && word.chars().all(|c| c == '!');
+
+    if let Some((rest, last)) = name.rsplit_once(char::is_whitespace) {
+        if is_shorthand(last) {
+            return (rest.trim().to_string(), last.parse().ok());
+        }
+    }
+    if let Some((first, rest)) = name.split_once(char::is_whitespace) {
+        if is_shorthand(first) {
+            return (rest.trim().to_string(), first.parse().ok());
+        }
+    }
+    (name.to_string(), None)
+}
@@ -0,0 +1,630 @@
+//! Filter expressions given with `--where`, for example
+//! `tag:work and due<+7d and not done and name~"deploy"`.
+//!
+//! An expression is made of comparisons between a task field and a value,
+//! joined with `and`, `or` and `not` and grouped with parentheses.
+//! Comparisons written one after the other without a keyword are joined with
+//! `and`. Values that contain spaces or parentheses go between double quotes.
+
+use std::fmt;
+
+use chrono::{Local, NaiveDate, NaiveTime};
+
+use crate::dates::{self, DateSettings};
+use crate::priority::Priority;
+use crate::{tags, Task};
+
+/// The task fields a query can look at.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Field {
+    Id,
+    Name,
+    Description,
+    Date,
+    Time,
+    Done,
+    Tag,
+    Priority,
+    /// Id of the parent task, 0 for top level tasks
+    Parent,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Field> {
+        match name.to_lowercase().as_str() {
+            "id" => Some(Field::Id),
+            "name" => Some(Field::Name),
+            "desc" | "description" => Some(Field::Description),
+            "due" | "date" => Some(Field::Date),
+            "time" => Some(Field::Time),
+            "done" => Some(Field::Done),
+            "tag" | "tags" => Some(Field::Tag),
+            "priority" | "prio" => Some(Field::Priority),
+            "parent" => Some(Field::Parent),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Field::Id => "id",
+            Field::Name => "name",
+            Field::Description => "desc",
+            Field::Date => "due",
+            Field::Time => "time",
+            Field::Done => "done",
+            Field::Tag => "tag",
+            Field::Priority => "priority",
+            Field::Parent => "parent",
+        }
+    }
+
+    /// Whether `op` makes sense for this field.
+    fn accepts(&self, op: Op) -> bool {
+        match op {
+            Op::Eq | Op::NotEq => true,
+            Op::Lt | Op::LtEq | Op::Gt | Op::GtEq => {
+                !matches!(self, Field::Done | Field::Tag)
+            }
+            Op::Contains => matches!(self, Field::Name | Field::Description | Field::Tag),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Op {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    /// Case insensitive substring match, written `~`
+    Contains,
+}
+
+impl Op {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            Op::NotEq => "!=",
+            Op::Lt => "<",
+            Op::LtEq => "<=",
+            Op::Gt => ">",
+            Op::GtEq => ">=",
+            Op::Contains => "~",
+        }
+    }
+
+    fn compare<T: Ord>(&self, left: &T, right: &T) -> bool {
+        match self {
+            Op::Eq => left == right,
+            Op::NotEq => left != right,
+            Op::Lt => left < right,
+            Op::LtEq => left <= right,
+            Op::Gt => left > right,
+            Op::GtEq => left >= right,
+            Op::Contains => false,
+        }
+    }
+}
+
+/// A value a field is compared against, already parsed for that field.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Value {
+    Number(u32),
+    Text(String),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    Bool(bool),
+    Priority(Priority),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Text(text) => {
+                write!(f, "\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+            Value::Date(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+            Value::Time(time) => write!(f, "{}", time.format("%H:%M")),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Priority(priority) => write!(f, "{}", priority),
+        }
+    }
+}
+
+/// A parsed filter expression.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Query {
+    /// Matches every task
+    All,
+    Compare(Field, Op, Value),
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    /// The id field (`Id` or `Parent`) is one of the ids, used to read the
+    /// tasks related to the ones shown. `--where` has no syntax for it
+    OneOf(Field, Vec<u32>),
+}
+
+impl Query {
+    /// Parse a `--where` expression, dates and times are read with the
+    /// configured formats.
+    pub fn parse(input: &str, settings: &DateSettings) -> Result<Query, String> {
+        let mut parser = Parser {
+            chars: input.chars().collect(),
+            position: 0,
+            settings,
+        };
+        let query = parser.parse_or()?;
+        parser.skip_whitespace();
+        if parser.position < parser.chars.len() {
+            return Err(parser.error("Unexpected ')'"));
+        }
+        Ok(query)
+    }
+
+    /// Parse the value of a `--where` option, no option matches every task.
+    pub fn parse_where(input: Option<String>, settings: &DateSettings) -> Result<Query, String> {
+        match input {
+            Some(input) => Query::parse(&input, settings),
+            None => Ok(Query::All),
+        }
+    }
+
+    /// Both queries have to match, `All` is left out.
+    pub fn and(self, other: Query) -> Query {
+        match (self, other) {
+            (Query::All, query) | (query, Query::All) => query,
+            (left, right) => Query::And(Box::new(left), Box::new(right)),
+        }
+    }
+
+    /// Either query has to match.
+    pub fn or(self, other: Query) -> Query {
+        match (self, other) {
+            (Query::All, _) | (_, Query::All) => Query::All,
+            (left, right) => Query::Or(Box::new(left), Box::new(right)),
+        }
+    }
+
+    pub fn negate(self) -> Query {
+        Query::Not(Box::new(self))
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        match self {
+            Query::All => true,
+            Query::Compare(field, op, value) => compare(task, *field, *op, value),
+            Query::Not(query) => !query.matches(task),
+            Query::And(left, right) => left.matches(task) && right.matches(task),
+            Query::Or(left, right) => left.matches(task) || right.matches(task),
+            Query::OneOf(field, ids) => ids
+                .iter()
+                .any(|id| compare(task, *field, Op::Eq, &Value::Number(*id))),
+        }
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // `and` binds tighter than `or` so only an `or` inside an `and` (or
+        // anything but a comparison inside a `not`) needs parentheses
+        let group = |query: &Query, wrap: bool| {
+            if wrap {
+                format!("({})", query)
+            } else {
+                query.to_string()
+            }
+        };
+        match self {
+            Query::All => write!(f, "all"),
+            Query::Compare(Field::Done, Op::Eq, Value::Bool(true)) => write!(f, "done"),
+            Query::Compare(field, op, value) => {
+                write!(f, "{}{}{}", field.name(), op.symbol(), value)
+            }
+            Query::Not(query) => {
+                write!(f, "not {}", group(query, !matches!(**query, Query::Compare(..))))
+            }
+            Query::And(left, right) => write!(
+                f,
+                "{} and {}",
+                group(left, matches!(**left, Query::Or(..))),
+                group(right, matches!(**right, Query::Or(..)))
+            ),
+            Query::Or(left, right) => write!(f, "{} or {}", left, right),
+            Query::OneOf(field, ids) => {
+                let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+                write!(f, "{} in ({})", field.name(), ids.join(", "))
+            }
+        }
+    }
+}
+
+fn compare(task: &Task, field: Field, op: Op, value: &Value) -> bool {
+    let text_matches = |text: &str, value: &str| {
+        let text = text.to_ascii_lowercase();
+        let value = value.to_ascii_lowercase();
+        match op {
+            Op::Contains => text.contains(&value),
+            _ => op.compare(&text, &value),
+        }
+    };
+
+    match (field, value) {
+        (Field::Id, Value::Number(number)) => op.compare(&task.id, number),
+        (Field::Parent, Value::Number(number)) => op.compare(&task.parent.unwrap_or(0), number),
+        (Field::Name, Value::Text(text)) => text_matches(&task.name, text),
+        (Field::Description, Value::Text(text)) => text_matches(&task.description, text),
+        (Field::Date, Value::Date(date)) => op.compare(&task.date, date),
+        (Field::Time, Value::Time(time)) => op.compare(&task.due_time(), time),
+        (Field::Done, Value::Bool(done)) => op.compare(&task.done, done),
+        (Field::Priority, Value::Priority(priority)) => op.compare(&task.priority, priority),
+        (Field::Tag, Value::Text(tag)) => match op {
+            Op::Eq => tags::has_tag(&task.tags, tag),
+            Op::NotEq => !tags::has_tag(&task.tags, tag),
+            _ => task.tags.iter().any(|task_tag| text_matches(task_tag.trim(), tag)),
+        },
+        _ => false,
+    }
+}
+
+/// Recursive descent parser over the characters of the expression.
+struct Parser<'a> {
+    chars: Vec<char>,
+    position: usize,
+    settings: &'a DateSettings,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("Invalid query: {} at position {}", message, self.position + 1)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn is_word_char(c: char) -> bool {
+        c.is_alphanumeric() || c == '_'
+    }
+
+    /// Read the run of letters, digits and underscores at the current position.
+    fn read_word(&mut self) -> String {
+        let start = self.position;
+        while self.peek().is_some_and(Self::is_word_char) {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+
+    /// Consume `keyword` if it is the next whole word.
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+        let start = self.position;
+        if self.read_word().eq_ignore_ascii_case(keyword) {
+            return true;
+        }
+        self.position = start;
+        false
+    }
+
+    fn eat_char(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn parse_or(&mut self) -> Result<Query, String> {
+        let mut query = self.parse_and()?;
+        while self.eat_keyword("or") {
+            query = Query::Or(Box::new(query), Box::new(self.parse_and()?));
+        }
+        Ok(query)
+    }
+
+    fn parse_and(&mut self) -> Result<Query, String> {
+        let mut query = self.parse_not()?;
+        loop {
+            self.skip_whitespace();
+            let start = self.position;
+            if self.peek().is_none() || self.peek() == Some(')') || self.eat_keyword("or") {
+                self.position = start;
+                return Ok(query);
+            }
+            self.eat_keyword("and");
+            query = Query::And(Box::new(query), Box::new(self.parse_not()?));
+        }
+    }
+
+    fn parse_not(&mut self) -> Result<Query, String> {
+        if self.eat_keyword("not") || self.eat_char('!') {
+            return Ok(Query::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Query, String> {
+        if self.eat_char('(') {
+            let query = self.parse_or()?;
+            if !self.eat_char(')') {
+                return Err(self.error("Missing ')'"));
+            }
+            return Ok(query);
+        }
+
+        self.skip_whitespace();
+        let start = self.position;
+        let name = self.read_word();
+        if name.is_empty() {
+            return Err(self.error("Expected a field"));
+        }
+        let field = Field::parse(&name).ok_or_else(|| {
+            self.position = start;
+            self.error(&format!(
+                "Unknown field '{}' (expected id, name, desc, due, time, done, tag or priority)",
+                name
+            ))
+        })?;
+
+        // two character operators first, so `priority:!!` still reads `:`
+        let op_start = self.position;
+        let rest: String = self.chars[op_start..].iter().take(2).collect();
+        let symbol = match ["!=", "<=", ">=", "=="].iter().find(|op| rest.starts_with(**op)) {
+            Some(op) => op.to_string(),
+            None => rest.chars().take(1).filter(|c| ":=!<>~".contains(*c)).collect(),
+        };
+        self.position += symbol.chars().count();
+        let op = match symbol.as_str() {
+            // a bare `done` is short for done=true
+            "" if field == Field::Done => {
+                return Ok(Query::Compare(field, Op::Eq, Value::Bool(true)))
+            }
+            "" => return Err(self.error(&format!("Expected an operator after '{}'", name))),
+            // `:` is the natural match for each field, a substring for text
+            // and equality for everything else
+            ":" if matches!(field, Field::Name | Field::Description) => Op::Contains,
+            ":" | "=" | "==" => Op::Eq,
+            "!=" => Op::NotEq,
+            "<" => Op::Lt,
+            "<=" => Op::LtEq,
+            ">" => Op::Gt,
+            ">=" => Op::GtEq,
+            "~" => Op::Contains,
+            _ => {
+                self.position = op_start;
+                return Err(self.error(&format!("Unknown operator '{}'", symbol)));
+            }
+        };
+        if !field.accepts(op) {
+            self.position = op_start;
+            return Err(self.error(&format!("'{}' can not be used with {}", symbol, name)));
+        }
+
+        let value_start = self.position;
+        let value = self.read_value()?;
+        let value = self.parse_value(field, &value).map_err(|e| {
+            self.position = value_start;
+            self.error(&e)
+        })?;
+        Ok(Query::Compare(field, op, value))
+    }
+
+    /// Read a quoted string or everything up to the next space or parenthesis.
+    fn read_value(&mut self) -> Result<String, String> {
+        let mut value = String::new();
+        if self.peek() == Some('"') {
+            self.position += 1;
+            loop {
+                match self.peek() {
+                    Some('"') => {
+                        self.position += 1;
+                        return Ok(value);
+                    }
+                    Some('\\') if self.chars.get(self.position + 1).is_some() => {
+                        value.push(self.chars[self.position + 1]);
+                        self.position += 2;
+                    }
+                    Some(c) => {
+                        value.push(c);
+                        self.position += 1;
+                    }
+                    None => return Err(self.error("Unterminated quoted value")),
+                }
+            }
+        }
+
+        while let Some(c) = self.peek().filter(|c| !c.is_whitespace() && !"()".contains(*c)) {
+            value.push(c);
+            self.position += 1;
+        }
+        if value.is_empty() {
+            return Err(self.error("Expected a value"));
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&self, field: Field, value: &str) -> Result<Value, String> {
+        match field {
+            Field::Id | Field::Parent => value
+                .parse::<u32>()
+                .map(Value::Number)
+                .map_err(|_| format!("Invalid id: {}", value)),
+            Field::Name | Field::Description => Ok(Value::Text(value.to_string())),
+            Field::Tag => Ok(Value::Text(tags::normalize_tag(value))),
+            Field::Date => parse_date_value(value, self.settings).map(Value::Date),
+            Field::Time => dates::parse_time_input(value, self.settings)?
+                .map(Value::Time)
+                .ok_or(String::from("Expected a time")),
+            Field::Priority => value.parse::<Priority>().map(Value::Priority),
+            Field::Done => match value.to_lowercase().as_str() {
+                "true" | "yes" | "1" => Ok(Value::Bool(true)),
+                "false" | "no" | "0" => Ok(Value::Bool(false)),
+                _ => Err(format!("Invalid done value: {} (expected true or false)", value)),
+            },
+        }
+    }
+}
+
+/// Parse a date value, which can be anything `create` accepts followed by an
+/// offset such as `today+3d`, `eom-1w` or `2026-01-31+1m`.
+fn parse_date_value(value: &str, settings: &DateSettings) -> Result<NaiveDate, String> {
+    let today = Local::now().date_naive();
+    let error = match dates::parse_date_relative(value, today, settings) {
+        Ok(date) => return Ok(date),
+        Err(error) => error,
+    };
+
+    let split = value.rfind(['+', '-']).filter(|split| *split > 0).ok_or(error.clone())?;
+    let (base, offset) = value.split_at(split);
+    dates::parse_date_relative(base, today, settings)
+        .and_then(|base| dates::parse_date_relative(offset, base, settings))
+        .map_err(|_| error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{SqliteStore, TaskStore};
+
+    fn parse(input: &str) -> Query {
+        Query::parse(input, &DateSettings::default()).unwrap()
+    }
+
+    fn task(id: u32, name: &str, date: &str, time: Option<&str>) -> Task {
+        Task {
+            id,
+            name: name.to_string(),
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            time: time.map(|time| NaiveTime::parse_from_str(time, "%H:%M").unwrap()),
+            description: String::new(),
+            done: false,
+            tags: Vec::new(),
+            priority: Priority::None,
+            recurrence: None,
+            parent: None,
+            depends_on: Vec::new(),
+        }
+    }
+
+    fn tasks() -> Vec<Task> {
+        let mut milk = task(1, "Buy milk", "2026-10-19", Some("09:30"));
+        milk.tags = vec![String::from("home"), String::from("errands")];
+        let mut deploy = task(2, "Deploy the API", "2026-10-20", None);
+        deploy.tags = vec![String::from("work")];
+        deploy.priority = Priority::High;
+        deploy.description = String::from("After the \"review\"");
+        let mut review = task(3, "review", "2026-10-20", Some("14:00"));
+        review.tags = vec![String::from("work"), String::from("code")];
+        review.priority = Priority::Urgent;
+        review.parent = Some(2);
+        review.done = true;
+        let mut plants = task(4, "Water plants", "2026-11-02", Some("08:00"));
+        plants.priority = Priority::Low;
+        plants.parent = Some(1);
+        vec![milk, deploy, review, plants]
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(parse("tag:a or tag:b and tag:c"), parse("tag:a or (tag:b and tag:c)"));
+        assert_eq!(parse("tag:a and tag:b or tag:c"), parse("(tag:a and tag:b) or tag:c"));
+        assert_ne!(parse("(tag:a or tag:b) and tag:c"), parse("tag:a or tag:b and tag:c"));
+        assert_eq!(parse("tag:a tag:b or tag:c"), parse("tag:a and tag:b or tag:c"));
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        assert_eq!(parse("not done and tag:a"), parse("(not done) and tag:a"));
+        assert_eq!(parse("!done or tag:a"), parse("(not done) or tag:a"));
+        assert_eq!(
+            parse("not (done or tag:a)"),
+            Query::Not(Box::new(parse("done or tag:a")))
+        );
+    }
+
+    #[test]
+    fn displays_the_groups_it_needs() {
+        let query = parse("(tag:a or tag:b) and not (done or name:x)");
+        assert_eq!(query.to_string(), "(tag=\"a\" or tag=\"b\") and not (done or name~\"x\")");
+        assert_eq!(parse(&query.to_string()), query);
+    }
+
+    #[test]
+    fn rejects_invalid_queries() {
+        let settings = DateSettings::default();
+        for input in [
+            "",
+            "tag:a or",
+            "(tag:a",
+            "tag:a)",
+            "colour=red",
+            "done<true",
+            "tag>a",
+            "priority=huge",
+            "id=-1",
+            "name=\"open",
+            "due<someday",
+        ] {
+            assert!(Query::parse(input, &settings).is_err(), "{} should not parse", input);
+        }
+    }
+
+    #[test]
+    fn sql_selects_the_same_tasks_as_matches() {
+        let tasks = tasks();
+        let mut store = SqliteStore::open(":memory:").unwrap();
+        store.write_all(&tasks).unwrap();
+
+        let mut queries: Vec<Query> = [
+            "tag:work",
+            "tag!=work",
+            "tag~WOR",
+            "name~milk",
+            "name=\"buy MILK\"",
+            "name<m",
+            "desc~review",
+            "due<2026-10-20",
+            "due>=2026-10-20 and due<=2026-10-31",
+            "time<10:00",
+            "time>=14:00",
+            "priority>=high",
+            "priority<medium",
+            "done",
+            "not done",
+            "done=false or priority=urgent",
+            "parent=0",
+            "parent!=0 and not tag:code",
+            "id>2",
+            "(tag:home or tag:code) and not done",
+        ]
+        .iter()
+        .map(|input| parse(input))
+        .collect();
+        queries.push(Query::OneOf(Field::Id, vec![1, 4, 9]));
+        queries.push(Query::OneOf(Field::Parent, vec![0, 2]));
+
+        for query in queries {
+            let expected: Vec<u32> = tasks
+                .iter()
+                .filter(|task| query.matches(task))
+                .map(|task| task.id)
+                .collect();
+            let mut selected: Vec<u32> =
+                store.query(&query).unwrap().iter().map(|task| task.id).collect();
+            selected.sort();
+            assert_eq!(selected, expected, "{}", query);
+        }
+    }
+}
@@ -0,0 +1,437 @@
+//! Recurrence rules for tasks that come back, such as `weekly on mon,thu`,
+//! `monthly on 2nd tue` or `every 3 days after completion`.
+//!
+//! Rules are stored as the text `Recurrence` displays as, so the csv and json
+//! lists stay readable and `kask update --repeat` takes the same text.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, Days, Duration, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::dates;
+
+/// Which occurrence of a weekday in a month, `Last` works for every month.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Nth {
+    Number(u32),
+    Last,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Recurrence {
+    /// Every `interval` days after the due date
+    Daily { interval: u32 },
+    /// Every `interval` weeks on the given days, the weekday of the due date
+    /// when there are none
+    Weekly { interval: u32, weekdays: Vec<Weekday> },
+    /// Every `interval` months on a day of the month, the day of the due date
+    /// when there is none. Days past the end of a month use its last day
+    MonthlyDay { interval: u32, day: Option<u32> },
+    /// Every `interval` months on a weekday such as the 2nd tuesday
+    MonthlyWeekday { interval: u32, nth: Nth, weekday: Weekday },
+    /// A number of days after the task was completed, whenever that was
+    AfterCompletion { days: u32 },
+}
+
+impl Recurrence {
+    /// The date the occurrence after one due on `due` falls on. Occurrences
+    /// that are already in the past on `today` are skipped.
+    pub fn next_date(&self, due: NaiveDate, today: NaiveDate) -> Option<NaiveDate> {
+        if let Recurrence::AfterCompletion { days } = self {
+            return today.checked_add_signed(Duration::days(*days as i64));
+        }
+
+        let mut next = self.date_after(due, due)?;
+        while next < today {
+            next = self.date_after(next, due)?;
+        }
+        Some(next)
+    }
+
+    /// First occurrence after `date` for a task first due on `due`.
+    fn date_after(&self, date: NaiveDate, due: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Recurrence::Daily { interval } => {
+                date.checked_add_signed(Duration::days(*interval as i64))
+            }
+            Recurrence::Weekly { interval, weekdays } => {
+                let weekdays = if weekdays.is_empty() {
+                    vec![due.weekday()]
+                } else {
+                    weekdays.clone()
+                };
+                let week_of = |date: NaiveDate| date.week(Weekday::Mon).first_day();
+                let first_week = week_of(due);
+                // only the weeks that are a multiple of the interval away from
+                // the first due date count, the next one is in the week of
+                // `date` or one interval later
+                let interval = *interval as i64;
+                let weeks = (week_of(date) - first_week).num_weeks();
+                let current = weeks.div_euclid(interval) * interval;
+                [current, current + interval]
+                    .into_iter()
+                    .filter_map(|week| first_week.checked_add_signed(Duration::weeks(week)))
+                    .flat_map(|monday| {
+                        (0..7).filter_map(move |day| monday.checked_add_days(Days::new(day)))
+                    })
+                    .find(|day| *day > date && weekdays.contains(&day.weekday()))
+            }
+            Recurrence::MonthlyDay { interval, day } => {
+                let day = day.unwrap_or(due.day());
+                self.month_after(date, due, *interval, |first| {
+                    let last = dates::end_of_month(first)?.day();
+                    first.with_day(day.min(last))
+                })
+            }
+            Recurrence::MonthlyWeekday {
+                interval,
+                nth,
+                weekday,
+            } => self.month_after(date, due, *interval, |first| nth_weekday(first, *nth, *weekday)),
+            Recurrence::AfterCompletion { days } => {
+                date.checked_add_signed(Duration::days(*days as i64))
+            }
+        }
+    }
+
+    /// First date after `date` that `day_in` picks in a month that is a
+    /// multiple of `interval` months away from the month of `due`.
+    fn month_after(
+        &self,
+        date: NaiveDate,
+        due: NaiveDate,
+        interval: u32,
+        day_in: impl Fn(NaiveDate) -> Option<NaiveDate>,
+    ) -> Option<NaiveDate> {
+        let month_number = |date: NaiveDate| date.year() as u32 * 12 + date.month0();
+        let first_month = due.with_day(1)?;
+        let start = month_number(date).saturating_sub(month_number(due)) / interval;
+        // look a few intervals ahead since a 5th weekday is not in every month
+        (start..start + 24)
+            .filter_map(|step| {
+                first_month.checked_add_months(Months::new(step.checked_mul(interval)?))
+            })
+            .filter_map(day_in)
+            .find(|day| *day > date)
+    }
+}
+
+/// The `nth` `weekday` of the month that starts on `first`.
+fn nth_weekday(first: NaiveDate, nth: Nth, weekday: Weekday) -> Option<NaiveDate> {
+    match nth {
+        Nth::Number(number) => {
+            let day = first
+                + Duration::days(dates::days_from(first.weekday(), weekday))
+                + Duration::weeks(number as i64 - 1);
+            Some(day).filter(|day| day.month() == first.month())
+        }
+        Nth::Last => {
+            let last = dates::end_of_month(first)?;
+            Some(last - Duration::days(dates::days_from(weekday, last.weekday())))
+        }
+    }
+}
+
+fn ordinal(number: u32) -> String {
+    let suffix = match (number % 10, number % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", number, suffix)
+}
+
+/// Read `15`, `15th`, `first` or `last`.
+fn parse_nth(word: &str) -> Option<Nth> {
+    match word {
+        "first" => Some(Nth::Number(1)),
+        "second" => Some(Nth::Number(2)),
+        "third" => Some(Nth::Number(3)),
+        "fourth" => Some(Nth::Number(4)),
+        "fifth" => Some(Nth::Number(5)),
+        "last" => Some(Nth::Last),
+        _ => {
+            let digits = word.trim_end_matches(|c: char| c.is_alphabetic());
+            let suffix = &word[digits.len()..];
+            if !["", "st", "nd", "rd", "th"].contains(&suffix) {
+                return None;
+            }
+            digits.parse::<u32>().ok().map(Nth::Number)
+        }
+    }
+}
+
+fn weekday_name(weekday: &Weekday) -> String {
+    weekday.to_string().to_lowercase()
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let every = |interval: u32, single: &str, unit: &str| {
+            if interval == 1 {
+                single.to_string()
+            } else {
+                format!("every {} {}", interval, unit)
+            }
+        };
+        match self {
+            Recurrence::Daily { interval } => write!(f, "{}", every(*interval, "daily", "days")),
+            Recurrence::Weekly { interval, weekdays } => {
+                write!(f, "{}", every(*interval, "weekly", "weeks"))?;
+                if !weekdays.is_empty() {
+                    let names: Vec<String> = weekdays.iter().map(weekday_name).collect();
+                    write!(f, " on {}", names.join(","))?;
+                }
+                Ok(())
+            }
+            Recurrence::MonthlyDay { interval, day } => {
+                write!(f, "{}", every(*interval, "monthly", "months"))?;
+                match day {
+                    Some(day) => write!(f, " on the {}", ordinal(*day)),
+                    None => Ok(()),
+                }
+            }
+            Recurrence::MonthlyWeekday {
+                interval,
+                nth,
+                weekday,
+            } => {
+                let nth = match nth {
+                    Nth::Number(number) => ordinal(*number),
+                    Nth::Last => String::from("last"),
+                };
+                write!(
+                    f,
+                    "{} on the {} {}",
+                    every(*interval, "monthly", "months"),
+                    nth,
+                    weekday_name(weekday)
+                )
+            }
+            Recurrence::AfterCompletion { days } => {
+                write!(f, "{} after completion", every(*days, "daily", "days"))
+            }
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let input = s.trim().to_lowercase().replace(',', " ");
+        let invalid = || {
+            format!(
+                "Invalid recurrence: {} (expected daily, weekly on mon thu, monthly on the 15th, \
+                 monthly on the 2nd tue, every 2 weeks or every 3 days after completion)",
+                s.trim()
+            )
+        };
+
+        // `<every> after completion`, `<every> on <days>` or just `<every>`
+        let (every, after_completion) = match input.strip_suffix("after completion") {
+            Some(every) => (every.trim(), true),
+            None => (input.as_str(), false),
+        };
+        let (every, on) = match every.split_once(" on ") {
+            Some((every, on)) => (every.trim(), Some(on.trim())),
+            None => (every.trim(), None),
+        };
+
+        let words: Vec<&str> = every.split_whitespace().collect();
+        let (interval, unit) = match words.as_slice() {
+            ["daily"] => (1, "day"),
+            ["weekly"] => (1, "week"),
+            ["monthly"] => (1, "month"),
+            ["every", unit] => (1, *unit),
+            ["every", interval, unit] => (interval.parse::<u32>().map_err(|_| invalid())?, *unit),
+            _ => return Err(invalid()),
+        };
+        if interval == 0 {
+            return Err(invalid());
+        }
+        let unit = unit.strip_suffix('s').unwrap_or(unit);
+
+        if after_completion {
+            let days = match unit {
+                "day" => interval,
+                "week" => interval.checked_mul(7).ok_or_else(invalid)?,
+                _ => return Err(invalid()),
+            };
+            if on.is_some() {
+                return Err(invalid());
+            }
+            return Ok(Recurrence::AfterCompletion { days });
+        }
+
+        let on_words: Vec<&str> = on
+            .unwrap_or("")
+            .split_whitespace()
+            .filter(|word| *word != "the")
+            .collect();
+        match (unit, on_words.as_slice()) {
+            ("day", []) => Ok(Recurrence::Daily { interval }),
+            ("week", weekdays) => {
+                let weekdays = weekdays
+                    .iter()
+                    .map(|weekday| dates::parse_weekday(weekday).ok_or_else(invalid))
+                    .collect::<Result<Vec<Weekday>, String>>()?;
+                Ok(Recurrence::Weekly { interval, weekdays })
+            }
+            ("month", []) => Ok(Recurrence::MonthlyDay {
+                interval,
+                day: None,
+            }),
+            ("month", [day]) => match parse_nth(day) {
+                Some(Nth::Number(day)) if (1..=31).contains(&day) => Ok(Recurrence::MonthlyDay {
+                    interval,
+                    day: Some(day),
+                }),
+                _ => Err(invalid()),
+            },
+            ("month", [nth, weekday]) => {
+                let nth = parse_nth(nth)
+                    .filter(|nth| !matches!(nth, Nth::Number(number) if !(1..=5).contains(number)))
+                    .ok_or_else(invalid)?;
+                let weekday = dates::parse_weekday(weekday).ok_or_else(invalid)?;
+                Ok(Recurrence::MonthlyWeekday {
+                    interval,
+                    nth,
+                    weekday,
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// `Task` is ordered, rules are compared through their text since weekdays
+/// have no order of their own.
+impl PartialOrd for Recurrence {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Recurrence {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_string().cmp(&other.to_string())
+    }
+}
+
+impl Serialize for Recurrence {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Recurrence {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Parse a rule given on the command line or read from a list, an empty value
+/// means the task does not repeat.
+pub fn parse_recurrence(value: &str) -> Result<Option<Recurrence>, String> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    value.parse().map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(input: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(input, "%Y-%m-%d").unwrap()
+    }
+
+    fn next(rule: &str, due: &str, today: &str) -> Option<NaiveDate> {
+        rule.parse::<Recurrence>().unwrap().next_date(date(due), date(today))
+    }
+
+    #[test]
+    fn weekly_rules_follow_their_weekdays_and_interval() {
+        assert_eq!(next("weekly on mon thu", "2026-10-19", "2026-10-19"), Some(date("2026-10-22")));
+        assert_eq!(next("weekly on mon thu", "2026-10-22", "2026-10-22"), Some(date("2026-10-26")));
+        assert_eq!(
+            next("every 2 weeks on mon", "2026-10-19", "2026-10-19"),
+            Some(date("2026-11-02"))
+        );
+        // thursday is in the first week, the monday after it is not
+        assert_eq!(
+            next("every 2 weeks on mon thu", "2026-10-19", "2026-10-19"),
+            Some(date("2026-10-22"))
+        );
+        assert_eq!(
+            next("every 2 weeks on mon thu", "2026-10-22", "2026-10-22"),
+            Some(date("2026-11-02"))
+        );
+    }
+
+    #[test]
+    fn occurrences_in_the_past_are_skipped() {
+        assert_eq!(next("every 2 weeks", "2026-10-19", "2026-10-28"), Some(date("2026-11-02")));
+        assert_eq!(next("every 2 weeks", "2026-10-19", "2026-11-03"), Some(date("2026-11-16")));
+        assert_eq!(next("every 3 days", "2026-10-01", "2026-10-18"), Some(date("2026-10-19")));
+        assert_eq!(
+            next("every 3 days after completion", "2026-10-01", "2026-10-18"),
+            Some(date("2026-10-21"))
+        );
+    }
+
+    #[test]
+    fn monthly_rules_pick_the_nth_weekday() {
+        assert_eq!(
+            next("monthly on the 2nd tue", "2026-10-13", "2026-10-13"),
+            Some(date("2026-11-10"))
+        );
+        assert_eq!(
+            next("monthly on the last fri", "2026-10-30", "2026-10-30"),
+            Some(date("2026-11-27"))
+        );
+        // october and november 2026 have only four tuesdays
+        assert_eq!(
+            next("monthly on the 5th tue", "2026-09-29", "2026-09-29"),
+            Some(date("2026-12-29"))
+        );
+        assert_eq!(
+            next("every 3 months on the 1st mon", "2026-10-05", "2026-10-05"),
+            Some(date("2027-01-04"))
+        );
+    }
+
+    #[test]
+    fn monthly_rules_stop_at_the_end_of_short_months() {
+        assert_eq!(next("monthly", "2027-01-31", "2027-01-31"), Some(date("2027-02-28")));
+        assert_eq!(next("monthly", "2028-01-31", "2028-01-31"), Some(date("2028-02-29")));
+        // the day of the first due date comes back once the month is long enough
+        let rule: Recurrence = "monthly".parse().unwrap();
+        assert_eq!(
+            rule.next_date(date("2027-02-28"), date("2027-02-28")),
+            Some(date("2027-03-28"))
+        );
+        assert_eq!(
+            next("monthly on the 31st", "2026-10-31", "2026-10-31"),
+            Some(date("2026-11-30"))
+        );
+    }
+
+    #[test]
+    fn intervals_past_the_calendar_end_the_series() {
+        assert_eq!(next("every 4000000000 months", "2026-10-18", "2026-10-18"), None);
+        assert_eq!(next("every 4000000000 weeks", "2026-10-18", "2026-10-18"), None);
+        assert_eq!(next("every 4000000000 days", "2026-10-18", "2026-10-18"), None);
+        assert!("every 1000000000 weeks after completion".parse::<Recurrence>().is_err());
+        assert!("every 0 days".parse::<Recurrence>().is_err());
+        assert!("monthly on the 6th mon".parse::<Recurrence>().is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+
+use chrono::NaiveDateTime;
+
+use crate::Task;
+
+/// Where a task stands compared to now, worked out from its date and time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Open and past its due date and time
+    Overdue,
+    /// Open and due later today
+    DueToday,
+    Upcoming,
+    Done,
+}
+
+impl Status {
+    /// Tasks without a time are due at the end of their day.
+    pub fn of(task: &Task, now: NaiveDateTime) -> Status {
+        if task.done {
+            Status::Done
+        } else if task.date.and_time(task.due_time()) < now {
+            Status::Overdue
+        } else if task.date == now.date() {
+            Status::DueToday
+        } else {
+            Status::Upcoming
+        }
+    }
+
+    /// Style of the status cell in a task table, see `prettytable::Cell::style_spec`.
+    pub fn style(&self) -> &'static str {
+        match self {
+            Status::Overdue => "Fr",
+            Status::DueToday => "Fy",
+            Status::Upcoming => "",
+            Status::Done => "Fg",
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Status::Overdue => "overdue",
+            Status::DueToday => "due today",
+            Status::Upcoming => "upcoming",
+            Status::Done => "done",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use super::*;
+    use crate::testing;
+
+    /// A moment on sunday 2026-10-18.
+    fn at(hour: u32, minute: u32, second: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(hour, minute, second)
+            .unwrap()
+    }
+
+    #[test]
+    fn done_tasks_are_done_whenever_they_were_due() {
+        let mut task = testing::task(1, "late", "2026-10-01");
+        task.done = true;
+        assert_eq!(Status::of(&task, at(12, 0, 0)), Status::Done);
+        task.date = NaiveDate::from_ymd_opt(2026, 10, 30).unwrap();
+        assert_eq!(Status::of(&task, at(12, 0, 0)), Status::Done);
+    }
+
+    #[test]
+    fn tasks_without_a_time_are_due_at_the_end_of_their_day() {
+        let task = testing::task(1, "today", "2026-10-18");
+        assert_eq!(Status::of(&task, at(0, 0, 0)), Status::DueToday);
+        assert_eq!(Status::of(&task, at(23, 59, 0)), Status::DueToday);
+        assert_eq!(Status::of(&task, at(23, 59, 30)), Status::Overdue);
+
+        let yesterday = testing::task(2, "yesterday", "2026-10-17");
+        assert_eq!(Status::of(&yesterday, at(0, 0, 0)), Status::Overdue);
+    }
+
+    #[test]
+    fn tasks_with_a_time_are_overdue_once_it_passed() {
+        let mut task = testing::task(1, "meeting", "2026-10-18");
+        task.time = NaiveTime::from_hms_opt(9, 30, 0);
+        assert_eq!(Status::of(&task, at(9, 0, 0)), Status::DueToday);
+        assert_eq!(Status::of(&task, at(9, 30, 0)), Status::DueToday);
+        assert_eq!(Status::of(&task, at(9, 31, 0)), Status::Overdue);
+    }
+
+    #[test]
+    fn later_days_are_upcoming() {
+        let mut task = testing::task(1, "tomorrow", "2026-10-19");
+        task.time = NaiveTime::from_hms_opt(0, 0, 0);
+        assert_eq!(Status::of(&task, at(23, 59, 59)), Status::Upcoming);
+    }
+}
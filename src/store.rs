@@ -0,0 +1,908 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use chrono::{NaiveDate, NaiveTime};
+use clap::ValueEnum;
+use rusqlite::{params, params_from_iter, Connection};
+
+use crate::priority::Priority;
+use crate::query::{Field, Op, Query, Value};
+use crate::recurrence::{self, Recurrence};
+use crate::{ids, journal, tags, trash, utils, Task, TIME_FORMAT};
+
+/// The storage formats a task list can use.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum Backend {
+    Csv,
+    Sqlite,
+    /// A single json array of tasks
+    Json,
+    /// One json task per line
+    Jsonl,
+}
+
+impl Backend {
+    /// Extension given to new lists that use this backend.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Backend::Csv => "csv",
+            Backend::Sqlite => "db",
+            Backend::Json => "json",
+            Backend::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// Storage backend for a single task list.
+pub trait TaskStore {
+    /// Load every task in the list.
+    fn load(&self) -> Result<Vec<Task>, String>;
+
+    /// Add a new task to the list.
+    fn insert(&mut self, task: &Task) -> Result<(), String>;
+
+    /// Replace the stored task that has the same id as `task`.
+    fn update(&mut self, task: &Task) -> Result<(), String>;
+
+    /// Remove the task with the given id.
+    fn delete(&mut self, id: u32) -> Result<(), String>;
+
+    /// Replace the whole list, used by commands that touch many tasks at once.
+    fn write_all(&mut self, tasks: &[Task]) -> Result<(), String>;
+
+    /// Load only the tasks that match `filter`.
+    fn query(&self, filter: &Query) -> Result<Vec<Task>, String> {
+        let tasks = self.load()?;
+        Ok(tasks.into_iter().filter(|task| filter.matches(task)).collect())
+    }
+
+    /// Id to give to the next task created in this list.
+    fn next_id(&self) -> Result<u32, String> {
+        let tasks = self.load()?;
+        ids::following(tasks.iter().map(|task| task.id).max().unwrap_or(0))
+    }
+}
+
+/// Lowercased extension of a task list path, used to pick its backend.
+fn path_extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Check the first bytes of a file for the sqlite database header.
+fn is_sqlite_file(path: &str) -> bool {
+    let mut header = [0u8; 16];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|_| &header == b"SQLite format 3\0")
+}
+
+/// Work out which backend an existing (or about to be created) list uses.
+/// Anything that is not recognized is treated as a csv list since that is what
+/// older versions created regardless of the file name.
+pub fn detect_backend(path: &str) -> Backend {
+    match path_extension(path).as_str() {
+        "db" | "sqlite" | "sqlite3" => Backend::Sqlite,
+        "json" => Backend::Json,
+        "jsonl" | "ndjson" => Backend::Jsonl,
+        _ if is_sqlite_file(path) => Backend::Sqlite,
+        _ => Backend::Csv,
+    }
+}
+
+/// Open the store for a task list, picking the backend from the file.
+pub fn open_store(path: &str) -> Result<Box<dyn TaskStore>, String> {
+    open_store_with_backend(path, detect_backend(path))
+}
+
+/// Path of the archive next to a list, `tasks.csv` is archived to
+/// `tasks.archive.csv`.
+pub fn archive_path(path: &str) -> String {
+    let list = Path::new(path);
+    match (list.file_stem(), list.extension()) {
+        (Some(stem), Some(extension)) => list
+            .with_file_name(format!(
+                "{}.archive.{}",
+                stem.to_string_lossy(),
+                extension.to_string_lossy()
+            ))
+            .to_string_lossy()
+            .to_string(),
+        _ => format!("{}.archive", path),
+    }
+}
+
+/// Open the archive of the list at `path`, it uses the same backend as the list.
+pub fn open_archive(path: &str) -> Result<Box<dyn TaskStore>, String> {
+    open_store_with_backend(&archive_path(path), detect_backend(path))
+}
+
+/// The archived tasks of the list at `path` that match `filter`, none when
+/// nothing was archived yet.
+pub fn query_archive(path: &str, filter: &Query) -> Result<Vec<Task>, String> {
+    if !Path::new(&archive_path(path)).exists() {
+        return Ok(Vec::new());
+    }
+    open_archive(path)?.query(filter)
+}
+
+/// `tasks` along with their subtasks and the tasks they depend on, which is
+/// what progress and blocked markers need. Only those are read from `store`,
+/// not the whole list.
+pub fn load_related(store: &dyn TaskStore, tasks: &[Task]) -> Result<Vec<Task>, String> {
+    if tasks.is_empty() {
+        return Ok(Vec::new());
+    }
+    let ids: HashSet<u32> = tasks.iter().map(|task| task.id).collect();
+    let mut targets: Vec<u32> = tasks
+        .iter()
+        .flat_map(|task| task.depends_on.iter().copied())
+        .filter(|id| !ids.contains(id))
+        .collect();
+    targets.sort();
+    targets.dedup();
+    let mut shown: Vec<u32> = ids.iter().copied().collect();
+    shown.sort();
+
+    let filter = Query::OneOf(Field::Parent, shown).or(Query::OneOf(Field::Id, targets));
+    let mut related = store.query(&filter)?;
+    related.retain(|task| !ids.contains(&task.id));
+    related.extend(tasks.iter().cloned());
+    Ok(related)
+}
+
+/// Open (creating it if needed) a store using a specific backend.
+pub fn open_store_with_backend(path: &str, backend: Backend) -> Result<Box<dyn TaskStore>, String> {
+    match backend {
+        Backend::Csv => Ok(Box::new(CsvStore::new(path))),
+        Backend::Sqlite => Ok(Box::new(SqliteStore::open(path)?)),
+        Backend::Json => Ok(Box::new(JsonStore::new(path, false))),
+        Backend::Jsonl => Ok(Box::new(JsonStore::new(path, true))),
+    }
+}
+
+/// Copy the list at `old_path` to a new list at `new_path` that uses
+/// `backend`. The archive, trash, journal and id counter follow the list, the
+/// old files are left in place. Returns the number of tasks copied.
+pub fn migrate(old_path: &str, new_path: &str, backend: Backend) -> Result<usize, String> {
+    let old_store = open_store(old_path)?;
+    let tasks = old_store.load()?;
+    open_store_with_backend(new_path, backend)?.write_all(&tasks)?;
+
+    let archived = query_archive(old_path, &Query::All)?;
+    if !archived.is_empty() {
+        open_store_with_backend(&archive_path(new_path), backend)?.write_all(&archived)?;
+    }
+    for (old_file, new_file) in [
+        (trash::trash_path(old_path), trash::trash_path(new_path)),
+        (journal::journal_path(old_path), journal::journal_path(new_path)),
+    ] {
+        if Path::new(&old_file).exists() {
+            fs::copy(&old_file, &new_file).map_err(|e| e.to_string())?;
+        }
+    }
+    ids::hand_out(new_path, ids::next_id(old_store.as_ref(), old_path)? - 1)?;
+    Ok(tasks.len())
+}
+
+/// Swap the task with the same id as `task` in a list that is rewritten in full.
+fn replace_task(tasks: &mut [Task], task: &Task) -> Result<(), String> {
+    let stored = tasks
+        .iter_mut()
+        .find(|stored| stored.id == task.id)
+        .ok_or(format!("Task {} not found", task.id))?;
+    *stored = task.clone();
+    Ok(())
+}
+
+fn remove_task(tasks: &mut Vec<Task>, id: u32) -> Result<(), String> {
+    let count = tasks.len();
+    tasks.retain(|task| task.id != id);
+    if tasks.len() == count {
+        return Err(format!("Task {} not found", id));
+    }
+    Ok(())
+}
+
+/// Task list stored as a csv file, see `utils::load_tasks_from_file`.
+pub struct CsvStore {
+    path: String,
+}
+
+impl CsvStore {
+    pub fn new(path: &str) -> CsvStore {
+        CsvStore {
+            path: path.to_string(),
+        }
+    }
+}
+
+impl TaskStore for CsvStore {
+    fn load(&self) -> Result<Vec<Task>, String> {
+        utils::load_tasks_from_file(&self.path)
+    }
+
+    fn insert(&mut self, task: &Task) -> Result<(), String> {
+        utils::append_task_to_file(task, &self.path)
+    }
+
+    fn update(&mut self, task: &Task) -> Result<(), String> {
+        let mut tasks = self.load()?;
+        replace_task(&mut tasks, task)?;
+        self.write_all(&tasks)
+    }
+
+    fn delete(&mut self, id: u32) -> Result<(), String> {
+        let mut tasks = self.load()?;
+        remove_task(&mut tasks, id)?;
+        self.write_all(&tasks)
+    }
+
+    fn write_all(&mut self, tasks: &[Task]) -> Result<(), String> {
+        utils::write_tasks_to_file(&self.path, tasks)
+    }
+}
+
+/// Hand edited json can have tags with stray whitespace or repeats, the other
+/// formats already trim them when they are read. A `;` is only a separator in
+/// the csv lists, so json tags are not split on it.
+fn normalize_task_tags(mut task: Task) -> Task {
+    task.tags = tags::clean_tags(task.tags);
+    task
+}
+
+/// Task list stored as json, either as one array or as one task per line.
+pub struct JsonStore {
+    path: String,
+    lines: bool,
+}
+
+impl JsonStore {
+    pub fn new(path: &str, lines: bool) -> JsonStore {
+        JsonStore {
+            path: path.to_string(),
+            lines,
+        }
+    }
+}
+
+impl TaskStore for JsonStore {
+    fn load(&self) -> Result<Vec<Task>, String> {
+        if !Path::new(&self.path).exists() {
+            return Ok(Vec::new());
+        }
+
+        let contents =
+            fs::read_to_string(&self.path).map_err(|e| format!("Error reading file: {}", e))?;
+
+        if !self.lines {
+            if contents.trim().is_empty() {
+                return Ok(Vec::new());
+            }
+            let tasks: Vec<Task> = serde_json::from_str(&contents)
+                .map_err(|e| format!("Error parsing file {}: {}", self.path, e))?;
+            return Ok(tasks.into_iter().map(normalize_task_tags).collect());
+        }
+
+        let mut tasks: Vec<Task> = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let task = serde_json::from_str(line)
+                .map_err(|e| format!("Error parsing line {} of {}: {}", number + 1, self.path, e))?;
+            tasks.push(normalize_task_tags(task));
+        }
+        Ok(tasks)
+    }
+
+    fn insert(&mut self, task: &Task) -> Result<(), String> {
+        if !self.lines {
+            let mut tasks = self.load()?;
+            tasks.push(task.clone());
+            return self.write_all(&tasks);
+        }
+
+        let line = serde_json::to_string(task).map_err(|e| e.to_string())?;
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.path)
+            .map_err(|e| format!("Error opening file: {}", e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Error writing to file: {}", e))
+    }
+
+    fn update(&mut self, task: &Task) -> Result<(), String> {
+        let mut tasks = self.load()?;
+        replace_task(&mut tasks, task)?;
+        self.write_all(&tasks)
+    }
+
+    fn delete(&mut self, id: u32) -> Result<(), String> {
+        let mut tasks = self.load()?;
+        remove_task(&mut tasks, id)?;
+        self.write_all(&tasks)
+    }
+
+    fn write_all(&mut self, tasks: &[Task]) -> Result<(), String> {
+        let mut contents = String::new();
+        if self.lines {
+            for task in tasks.iter() {
+                contents.push_str(&serde_json::to_string(task).map_err(|e| e.to_string())?);
+                contents.push('\n');
+            }
+        } else {
+            contents = serde_json::to_string_pretty(tasks).map_err(|e| e.to_string())?;
+            contents.push('\n');
+        }
+        fs::write(&self.path, contents).map_err(|e| format!("Error writing to file: {}", e))
+    }
+}
+
+const SQLITE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS tasks (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        date TEXT NOT NULL,
+        time TEXT NOT NULL,
+        description TEXT NOT NULL DEFAULT '',
+        done INTEGER NOT NULL DEFAULT 0,
+        priority INTEGER NOT NULL DEFAULT 0,
+        recurrence TEXT NOT NULL DEFAULT '',
+        parent INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS task_tags (
+        task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (task_id, position)
+    );
+    CREATE TABLE IF NOT EXISTS task_dependencies (
+        task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+        depends_on INTEGER NOT NULL,
+        PRIMARY KEY (task_id, depends_on)
+    );
+    CREATE INDEX IF NOT EXISTS tasks_date_index ON tasks(date, time);
+    CREATE INDEX IF NOT EXISTS tasks_done_index ON tasks(done, date);
+    CREATE INDEX IF NOT EXISTS task_tags_tag_index ON task_tags(tag, task_id);
+";
+
+/// Task list stored in a sqlite database. Dates are kept as `yyyy-mm-dd` and
+/// times as `hh:mm` so that they sort correctly and range filters can use the
+/// index on the date column.
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+fn sqlite_error(error: rusqlite::Error) -> String {
+    format!("Database error: {}", error)
+}
+
+fn date_to_sql(date: &NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn date_from_sql(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date in database: {}", date))
+}
+
+fn time_to_sql(time: &Option<NaiveTime>) -> String {
+    time.map(|time| time.format("%H:%M").to_string()).unwrap_or_default()
+}
+
+fn time_from_sql(time: &str) -> Result<Option<NaiveTime>, String> {
+    if time.is_empty() {
+        return Ok(None);
+    }
+    // databases written before times were typed hold the csv format
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(time, TIME_FORMAT))
+        .map(Some)
+        .map_err(|_| format!("Invalid time in database: {}", time))
+}
+
+fn recurrence_to_sql(recurrence: &Option<Recurrence>) -> String {
+    recurrence.as_ref().map(|recurrence| recurrence.to_string()).unwrap_or_default()
+}
+
+/// Turn a query into a sql condition over the tasks table, pushing the text
+/// values it needs to `values` so they are bound instead of pasted in.
+fn query_to_sql(query: &Query, values: &mut Vec<String>) -> String {
+    fn bind(values: &mut Vec<String>, value: String) -> String {
+        values.push(value);
+        format!("?{}", values.len())
+    }
+
+    match query {
+        Query::All => String::from("1"),
+        Query::Not(query) => format!("NOT ({})", query_to_sql(query, values)),
+        Query::And(left, right) => format!(
+            "({}) AND ({})",
+            query_to_sql(left, values),
+            query_to_sql(right, values)
+        ),
+        Query::Or(left, right) => format!(
+            "({}) OR ({})",
+            query_to_sql(left, values),
+            query_to_sql(right, values)
+        ),
+        Query::OneOf(field, ids) => {
+            let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+            match field {
+                Field::Id => format!("id IN ({})", ids.join(", ")),
+                Field::Parent => format!("COALESCE(parent, 0) IN ({})", ids.join(", ")),
+                _ => String::from("0"),
+            }
+        }
+        Query::Compare(field, op, value) => {
+            let operator = match op {
+                Op::Eq => "=",
+                Op::NotEq => "!=",
+                Op::Lt => "<",
+                Op::LtEq => "<=",
+                Op::Gt => ">",
+                Op::GtEq => ">=",
+                Op::Contains => "",
+            };
+            let has_tag = "EXISTS (SELECT 1 FROM task_tags WHERE task_tags.task_id = tasks.id AND";
+            match (field, op, value) {
+                (Field::Id, _, Value::Number(number)) => format!("id {} {}", operator, number),
+                (Field::Parent, _, Value::Number(number)) => {
+                    format!("COALESCE(parent, 0) {} {}", operator, number)
+                }
+                (Field::Done, _, Value::Bool(done)) => format!("done {} {}", operator, *done as u8),
+                (Field::Priority, _, Value::Priority(priority)) => {
+                    format!("priority {} {}", operator, priority.level())
+                }
+                (Field::Date, _, Value::Date(date)) => {
+                    format!("date {} {}", operator, bind(values, date_to_sql(date)))
+                }
+                // tasks without a time are due at the end of their day
+                (Field::Time, _, Value::Time(time)) => format!(
+                    "COALESCE(NULLIF(time, ''), '{}') {} {}",
+                    crate::DEFAULT_TIME.format("%H:%M"),
+                    operator,
+                    bind(values, time_to_sql(&Some(*time)))
+                ),
+                (Field::Tag, Op::Eq, Value::Text(tag)) => {
+                    format!("{} tag = {})", has_tag, bind(values, tag.clone()))
+                }
+                (Field::Tag, Op::NotEq, Value::Text(tag)) => {
+                    format!("NOT {} tag = {})", has_tag, bind(values, tag.clone()))
+                }
+                (Field::Tag, _, Value::Text(tag)) => format!(
+                    "{} instr(lower(tag), lower({})) > 0)",
+                    has_tag,
+                    bind(values, tag.clone())
+                ),
+                (Field::Name | Field::Description, _, Value::Text(text)) => {
+                    let column = if *field == Field::Name { "name" } else { "description" };
+                    let text = bind(values, text.clone());
+                    match op {
+                        Op::Contains => format!("instr(lower({}), lower({})) > 0", column, text),
+                        _ => format!("lower({}) {} lower({})", column, operator, text),
+                    }
+                }
+                // the parser never pairs a field with another kind of value
+                _ => String::from("0"),
+            }
+        }
+    }
+}
+
+impl SqliteStore {
+    pub fn open(path: &str) -> Result<SqliteStore, String> {
+        let connection = Connection::open(path).map_err(sqlite_error)?;
+        connection
+            .execute_batch("PRAGMA foreign_keys = ON;")
+            .map_err(sqlite_error)?;
+        connection.execute_batch(SQLITE_SCHEMA).map_err(sqlite_error)?;
+        Self::migrate(&connection)?;
+        Ok(SqliteStore { connection })
+    }
+
+    /// Add the columns that databases created by older versions are missing.
+    fn migrate(connection: &Connection) -> Result<(), String> {
+        let mut statement = connection
+            .prepare("SELECT name FROM pragma_table_info('tasks')")
+            .map_err(sqlite_error)?;
+        let columns = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(sqlite_error)?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(sqlite_error)?;
+        let added_columns = [
+            ("priority", "INTEGER NOT NULL DEFAULT 0"),
+            ("recurrence", "TEXT NOT NULL DEFAULT ''"),
+            ("parent", "INTEGER"),
+        ];
+        for (name, definition) in added_columns {
+            if !columns.iter().any(|column| column == name) {
+                let sql = format!("ALTER TABLE tasks ADD COLUMN {} {};", name, definition);
+                connection.execute_batch(&sql).map_err(sqlite_error)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run a select over the tasks table and attach the tags of every task found.
+    fn select_tasks(&self, where_clause: &str, values: &[String]) -> Result<Vec<Task>, String> {
+        let sql = format!(
+            "SELECT id, name, date, time, description, done, priority, recurrence, parent \
+             FROM tasks {} ORDER BY date, time, id",
+            where_clause
+        );
+        let mut statement = self.connection.prepare(&sql).map_err(sqlite_error)?;
+        let rows = statement
+            .query_map(params_from_iter(values.iter()), |row| {
+                Ok((
+                    row.get::<_, u32>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, bool>(5)?,
+                    row.get::<_, u8>(6)?,
+                    row.get::<_, String>(7)?,
+                    row.get::<_, Option<u32>>(8)?,
+                ))
+            })
+            .map_err(sqlite_error)?;
+
+        let mut tasks: Vec<Task> = Vec::new();
+        for row in rows {
+            let (id, name, date, time, description, done, priority, recurrence, parent) =
+                row.map_err(sqlite_error)?;
+            tasks.push(Task {
+                id,
+                name,
+                date: date_from_sql(&date)?,
+                time: time_from_sql(&time)?,
+                description,
+                done,
+                tags: Vec::new(),
+                priority: Priority::from_level(priority)
+                    .ok_or(format!("Invalid priority in database: {}", priority))?,
+                recurrence: recurrence::parse_recurrence(&recurrence)?,
+                parent,
+                depends_on: Vec::new(),
+            });
+        }
+
+        let sql = format!(
+            "SELECT task_id, tag FROM task_tags WHERE task_id IN (SELECT id FROM tasks {}) \
+             ORDER BY task_id, position",
+            where_clause
+        );
+        let mut statement = self.connection.prepare(&sql).map_err(sqlite_error)?;
+        let rows = statement
+            .query_map(params_from_iter(values.iter()), |row| {
+                Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(sqlite_error)?;
+
+        let mut tags: HashMap<u32, Vec<String>> = HashMap::new();
+        for row in rows {
+            let (task_id, tag) = row.map_err(sqlite_error)?;
+            tags.entry(task_id).or_default().push(tag);
+        }
+        for task in tasks.iter_mut() {
+            task.tags = tags.remove(&task.id).unwrap_or_default();
+        }
+
+        let sql = format!(
+            "SELECT task_id, depends_on FROM task_dependencies \
+             WHERE task_id IN (SELECT id FROM tasks {}) ORDER BY task_id, rowid",
+            where_clause
+        );
+        let mut statement = self.connection.prepare(&sql).map_err(sqlite_error)?;
+        let rows = statement
+            .query_map(params_from_iter(values.iter()), |row| {
+                Ok((row.get::<_, u32>(0)?, row.get::<_, u32>(1)?))
+            })
+            .map_err(sqlite_error)?;
+
+        let mut dependencies: HashMap<u32, Vec<u32>> = HashMap::new();
+        for row in rows {
+            let (task_id, depends_on) = row.map_err(sqlite_error)?;
+            dependencies.entry(task_id).or_default().push(depends_on);
+        }
+        for task in tasks.iter_mut() {
+            task.depends_on = dependencies.remove(&task.id).unwrap_or_default();
+        }
+
+        Ok(tasks)
+    }
+
+    fn insert_with(connection: &Connection, task: &Task) -> Result<(), String> {
+        connection
+            .execute(
+                "INSERT INTO tasks \
+                 (id, name, date, time, description, done, priority, recurrence, parent) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    task.id,
+                    task.name.trim(),
+                    date_to_sql(&task.date),
+                    time_to_sql(&task.time),
+                    task.description.trim(),
+                    task.done,
+                    task.priority.level(),
+                    recurrence_to_sql(&task.recurrence),
+                    task.parent
+                ],
+            )
+            .map_err(sqlite_error)?;
+        Self::insert_tags_with(connection, task)?;
+        Self::insert_dependencies_with(connection, task)
+    }
+
+    fn insert_tags_with(connection: &Connection, task: &Task) -> Result<(), String> {
+        for (position, tag) in task.tags.iter().enumerate() {
+            connection
+                .execute(
+                    "INSERT INTO task_tags (task_id, position, tag) VALUES (?1, ?2, ?3)",
+                    params![task.id, position, tag.trim()],
+                )
+                .map_err(sqlite_error)?;
+        }
+        Ok(())
+    }
+
+    fn insert_dependencies_with(connection: &Connection, task: &Task) -> Result<(), String> {
+        for depends_on in task.depends_on.iter() {
+            connection
+                .execute(
+                    "INSERT OR IGNORE INTO task_dependencies (task_id, depends_on) \
+                     VALUES (?1, ?2)",
+                    params![task.id, depends_on],
+                )
+                .map_err(sqlite_error)?;
+        }
+        Ok(())
+    }
+}
+
+impl TaskStore for SqliteStore {
+    fn load(&self) -> Result<Vec<Task>, String> {
+        self.select_tasks("", &[])
+    }
+
+    fn query(&self, filter: &Query) -> Result<Vec<Task>, String> {
+        let mut values: Vec<String> = Vec::new();
+        let where_clause = match filter {
+            Query::All => String::new(),
+            filter => format!("WHERE {}", query_to_sql(filter, &mut values)),
+        };
+        self.select_tasks(&where_clause, &values)
+    }
+
+    fn insert(&mut self, task: &Task) -> Result<(), String> {
+        let transaction = self.connection.transaction().map_err(sqlite_error)?;
+        Self::insert_with(&transaction, task)?;
+        transaction.commit().map_err(sqlite_error)
+    }
+
+    fn update(&mut self, task: &Task) -> Result<(), String> {
+        let transaction = self.connection.transaction().map_err(sqlite_error)?;
+        let changed = transaction
+            .execute(
+                "UPDATE tasks SET name = ?2, date = ?3, time = ?4, description = ?5, done = ?6, \
+                 priority = ?7, recurrence = ?8, parent = ?9 WHERE id = ?1",
+                params![
+                    task.id,
+                    task.name.trim(),
+                    date_to_sql(&task.date),
+                    time_to_sql(&task.time),
+                    task.description.trim(),
+                    task.done,
+                    task.priority.level(),
+                    recurrence_to_sql(&task.recurrence),
+                    task.parent
+                ],
+            )
+            .map_err(sqlite_error)?;
+        if changed == 0 {
+            return Err(format!("Task {} not found", task.id));
+        }
+        transaction
+            .execute("DELETE FROM task_tags WHERE task_id = ?1", params![task.id])
+            .map_err(sqlite_error)?;
+        transaction
+            .execute("DELETE FROM task_dependencies WHERE task_id = ?1", params![task.id])
+            .map_err(sqlite_error)?;
+        Self::insert_tags_with(&transaction, task)?;
+        Self::insert_dependencies_with(&transaction, task)?;
+        transaction.commit().map_err(sqlite_error)
+    }
+
+    fn delete(&mut self, id: u32) -> Result<(), String> {
+        let changed = self
+            .connection
+            .execute("DELETE FROM tasks WHERE id = ?1", params![id])
+            .map_err(sqlite_error)?;
+        if changed == 0 {
+            return Err(format!("Task {} not found", id));
+        }
+        Ok(())
+    }
+
+    fn write_all(&mut self, tasks: &[Task]) -> Result<(), String> {
+        let transaction = self.connection.transaction().map_err(sqlite_error)?;
+        transaction
+            .execute_batch(
+                "DELETE FROM task_tags; DELETE FROM task_dependencies; DELETE FROM tasks;",
+            )
+            .map_err(sqlite_error)?;
+        for task in tasks.iter() {
+            Self::insert_with(&transaction, task)?;
+        }
+        transaction.commit().map_err(sqlite_error)
+    }
+
+    fn next_id(&self) -> Result<u32, String> {
+        let last = self
+            .connection
+            .query_row("SELECT COALESCE(MAX(id), 0) FROM tasks", [], |row| row.get(0))
+            .map_err(sqlite_error)?;
+        ids::following(last)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    /// Tasks that use every field, with a subtask and a dependency.
+    fn tasks() -> Vec<Task> {
+        let mut report = testing::task(1, "Write the \"report\", part 1", "2026-10-20");
+        report.time = NaiveTime::from_hms_opt(9, 30, 0);
+        report.description = String::from("two\nlines");
+        report.tags = vec![String::from("work"), String::from("with space")];
+        report.priority = Priority::High;
+        report.recurrence = recurrence::parse_recurrence("every 2 weeks on mon thu").unwrap();
+        let mut draft = testing::task(2, "draft", "2099-01-01");
+        draft.parent = Some(1);
+        draft.done = true;
+        let mut review = testing::task(3, "review", "1900-02-28");
+        review.depends_on = vec![1, 2];
+        vec![report, draft, review]
+    }
+
+    /// Every task of `store` in id order, sqlite reads them in date order.
+    fn load(store: &dyn TaskStore) -> Vec<Task> {
+        let mut tasks = store.load().unwrap();
+        tasks.sort();
+        tasks
+    }
+
+    #[test]
+    fn sqlite_round_trips_inserts_updates_and_deletes() {
+        let mut store = SqliteStore::open(":memory:").unwrap();
+        let mut tasks = tasks();
+        for task in tasks.iter() {
+            store.insert(task).unwrap();
+        }
+        assert_eq!(load(&store), tasks);
+        assert_eq!(store.next_id(), Ok(4));
+
+        tasks[0].tags = vec![String::from("home")];
+        tasks[0].time = None;
+        tasks[0].recurrence = None;
+        tasks[2].depends_on = vec![2];
+        store.update(&tasks[0]).unwrap();
+        store.update(&tasks[2]).unwrap();
+        assert_eq!(load(&store), tasks);
+
+        store.delete(2).unwrap();
+        tasks.remove(1);
+        assert_eq!(load(&store), tasks);
+        assert!(store.update(&testing::task(9, "missing", "2026-10-20")).is_err());
+
+        store.write_all(&tasks[..1]).unwrap();
+        assert_eq!(load(&store), tasks[..1].to_vec());
+    }
+
+    #[test]
+    fn sqlite_queries_filter_by_date() {
+        let mut store = SqliteStore::open(":memory:").unwrap();
+        store.write_all(&tasks()).unwrap();
+        let filter = Query::Compare(
+            Field::Date,
+            Op::GtEq,
+            Value::Date(NaiveDate::from_ymd_opt(2026, 1, 1).unwrap()),
+        );
+        let ids: Vec<u32> = store.query(&filter).unwrap().iter().map(|task| task.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn migrating_keeps_tasks_archive_trash_and_ids() {
+        let dir = TempDir::new();
+        let csv_path = dir.path("tasks.csv");
+        // the csv lists only hold two digit years
+        let mut tasks = tasks();
+        tasks[1].date = NaiveDate::from_ymd_opt(2030, 1, 1).unwrap();
+        tasks[2].date = NaiveDate::from_ymd_opt(2026, 11, 1).unwrap();
+        open_store(&csv_path).unwrap().write_all(&tasks).unwrap();
+        let mut archived = testing::task(7, "archived", "2026-09-01");
+        archived.done = true;
+        open_archive(&csv_path).unwrap().insert(&archived).unwrap();
+        fs::write(trash::trash_path(&csv_path), "").unwrap();
+        fs::write(journal::journal_path(&csv_path), "").unwrap();
+
+        let sqlite_path = dir.path("tasks.db");
+        assert_eq!(migrate(&csv_path, &sqlite_path, Backend::Sqlite), Ok(3));
+        let json_path = dir.path("tasks.json");
+        assert_eq!(migrate(&sqlite_path, &json_path, Backend::Json), Ok(3));
+
+        assert_eq!(load(open_store(&sqlite_path).unwrap().as_ref()), tasks);
+        assert_eq!(load(open_store(&json_path).unwrap().as_ref()), tasks);
+        assert_eq!(query_archive(&json_path, &Query::All).unwrap(), vec![archived]);
+        assert!(Path::new(&trash::trash_path(&json_path)).exists());
+        assert!(Path::new(&journal::journal_path(&json_path)).exists());
+        let json_store = open_store(&json_path).unwrap();
+        assert_eq!(ids::next_id(json_store.as_ref(), &json_path), Ok(8));
+        // the old list is left in place
+        assert_eq!(load(open_store(&csv_path).unwrap().as_ref()), tasks);
+    }
+
+    #[test]
+    fn json_and_json_lines_round_trip() {
+        let dir = TempDir::new();
+        for (name, lines) in [("tasks.json", false), ("tasks.jsonl", true)] {
+            let path = dir.path(name);
+            let mut store = JsonStore::new(&path, lines);
+            let mut tasks = tasks();
+            store.write_all(&tasks[..2]).unwrap();
+            store.insert(&tasks[2]).unwrap();
+            assert_eq!(store.load().unwrap(), tasks, "{}", name);
+
+            tasks[1].name = String::from("renamed");
+            store.update(&tasks[1]).unwrap();
+            store.delete(1).unwrap();
+            tasks.remove(0);
+            assert_eq!(JsonStore::new(&path, lines).load().unwrap(), tasks, "{}", name);
+            assert!(store.delete(1).is_err());
+        }
+    }
+
+    #[test]
+    fn json_dates_are_written_in_full_and_read_in_both_forms() {
+        let dir = TempDir::new();
+        let path = dir.path("tasks.json");
+        JsonStore::new(&path, false).write_all(&tasks()[1..2]).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("\"2099-01-01\""));
+
+        // lists written before the dates were in full use mm/dd/yy
+        let path = dir.file(
+            "old.jsonl",
+            "{\"id\":1,\"name\":\"old\",\"date\":\"10/20/26\",\"time\":\"09:30am\",\
+              \"done\":false}\n",
+        );
+        let tasks = JsonStore::new(&path, true).load().unwrap();
+        assert_eq!(tasks[0].date, NaiveDate::from_ymd_opt(2026, 10, 20).unwrap());
+        assert_eq!(tasks[0].time, NaiveTime::from_hms_opt(9, 30, 0));
+    }
+
+    #[test]
+    fn json_tags_are_trimmed_but_not_split() {
+        let dir = TempDir::new();
+        let path = dir.file(
+            "tasks.json",
+            "[{\"id\":1,\"name\":\"a\",\"date\":\"2026-10-20\",\"done\":false,\
+              \"tags\":[\" work \",\"work\",\"a;b\",\"\",\"Work\"]}]",
+        );
+        let tasks = JsonStore::new(&path, false).load().unwrap();
+        assert_eq!(tasks[0].tags, vec!["work", "a;b", "Work"]);
+    }
+}
@@ -0,0 +1,258 @@
+//! Parent and child tasks. A task points at its parent through `Task::parent`,
+//! the children of a task are every task that points at it.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::Task;
+
+pub fn children_of(tasks: &[Task], id: u32) -> Vec<u32> {
+    tasks
+        .iter()
+        .filter(|task| task.parent == Some(id) && task.id != id)
+        .map(|task| task.id)
+        .collect()
+}
+
+pub fn has_open_subtasks(tasks: &[Task], id: u32) -> bool {
+    tasks
+        .iter()
+        .any(|task| task.parent == Some(id) && task.id != id && !task.done)
+}
+
+/// Every task below `id`, children first and then their own children.
+pub fn descendants_of(tasks: &[Task], id: u32) -> Vec<u32> {
+    let mut found: Vec<u32> = Vec::new();
+    let mut pending = children_of(tasks, id);
+    while let Some(child) = pending.pop() {
+        // a hand edited list can have a loop, never visit a task twice
+        if child == id || found.contains(&child) {
+            continue;
+        }
+        found.push(child);
+        pending.extend(children_of(tasks, child));
+    }
+    found
+}
+
+/// Move `id` under `parent`, or to the top level when `parent` is `None`.
+pub fn set_parent(tasks: &mut [Task], id: u32, parent: Option<u32>) -> Result<(), String> {
+    if let Some(parent) = parent {
+        if !tasks.iter().any(|task| task.id == parent) {
+            return Err(format!("Parent task {} not found", parent));
+        }
+        if parent == id || descendants_of(tasks, id).contains(&parent) {
+            return Err(format!("Task {} can not be moved under its own subtask {}", id, parent));
+        }
+    }
+    let task = tasks
+        .iter_mut()
+        .find(|task| task.id == id)
+        .ok_or(format!("Task {} not found", id))?;
+    task.parent = parent;
+    Ok(())
+}
+
+/// Number of done and total direct children of every task that has any.
+pub fn progress(tasks: &[Task]) -> HashMap<u32, (usize, usize)> {
+    let mut progress: HashMap<u32, (usize, usize)> = HashMap::new();
+    for task in tasks.iter() {
+        if let Some(parent) = task.parent.filter(|parent| *parent != task.id) {
+            let (done, total) = progress.entry(parent).or_default();
+            *done += task.done as usize;
+            *total += 1;
+        }
+    }
+    progress
+}
+
+/// Put every task right after its parent, keeping the order of `tasks` among
+/// siblings, and return each with its depth. Tasks whose parent is not in
+/// `tasks` are shown at the top level.
+pub fn nest(tasks: Vec<Task>) -> Vec<(usize, Task)> {
+    let ids: HashSet<u32> = tasks.iter().map(|task| task.id).collect();
+    let order: Vec<u32> = tasks.iter().map(|task| task.id).collect();
+    let mut roots: Vec<Task> = Vec::new();
+    let mut children: HashMap<u32, Vec<Task>> = HashMap::new();
+    for task in tasks {
+        match task.parent.filter(|parent| ids.contains(parent) && *parent != task.id) {
+            Some(parent) => children.entry(parent).or_default().push(task),
+            None => roots.push(task),
+        }
+    }
+
+    fn visit(
+        task: Task,
+        depth: usize,
+        children: &mut HashMap<u32, Vec<Task>>,
+        rows: &mut Vec<(usize, Task)>,
+    ) {
+        let id = task.id;
+        rows.push((depth, task));
+        for child in children.remove(&id).unwrap_or_default() {
+            visit(child, depth + 1, children, rows);
+        }
+    }
+
+    let mut rows: Vec<(usize, Task)> = Vec::new();
+    for root in roots {
+        visit(root, 0, &mut children, &mut rows);
+    }
+
+    // only tasks that are their own ancestor are left, show them at the top
+    for id in order {
+        let left = children
+            .values_mut()
+            .find_map(|siblings| {
+                let position = siblings.iter().position(|task| task.id == id)?;
+                Some(siblings.remove(position))
+            });
+        if let Some(task) = left {
+            visit(task, 0, &mut children, &mut rows);
+        }
+    }
+    rows
+}
+
+/// Remove `id` from the list. Its subtasks are removed with it when `cascade`
+/// is set, otherwise they move up to the parent of the removed task. Returns
+/// the ids of every removed task.
+pub fn remove_task(tasks: &mut Vec<Task>, id: u32, cascade: bool) -> Result<Vec<u32>, String> {
+    let parent = tasks
+        .iter()
+        .find(|task| task.id == id)
+        .ok_or(format!("Task {} not found", id))?
+        .parent;
+
+    let mut removed = vec![id];
+    if cascade {
+        removed.extend(descendants_of(tasks, id));
+    } else {
+        for task in tasks.iter_mut().filter(|task| task.parent == Some(id)) {
+            task.parent = parent.filter(|parent| *parent != id);
+        }
+    }
+    tasks.retain(|task| !removed.contains(&task.id));
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit;
+    use crate::store::{self, SqliteStore, TaskStore};
+    use crate::testing::{self, TempDir};
+
+    /// 1 has the subtasks 3 and 2, 2 has the subtask 4 and the parent of 5 is gone.
+    fn tasks() -> Vec<Task> {
+        let mut tasks: Vec<Task> = [3, 1, 2, 4, 5]
+            .iter()
+            .map(|id| testing::task(*id, &format!("task {}", id), "2026-10-20"))
+            .collect();
+        tasks[0].parent = Some(1);
+        tasks[2].parent = Some(1);
+        tasks[3].parent = Some(2);
+        tasks[4].parent = Some(9);
+        tasks
+    }
+
+    fn ids(tasks: &[Task]) -> Vec<u32> {
+        let mut ids: Vec<u32> = tasks.iter().map(|task| task.id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn nests_subtasks_under_their_parent() {
+        let rows: Vec<(usize, u32)> =
+            nest(tasks()).into_iter().map(|(depth, task)| (depth, task.id)).collect();
+        assert_eq!(rows, vec![(0, 1), (1, 3), (1, 2), (2, 4), (0, 5)]);
+
+        // a hand edited loop is still shown once
+        let mut tasks = vec![
+            testing::task(6, "six", "2026-10-20"),
+            testing::task(7, "seven", "2026-10-20"),
+        ];
+        tasks[0].parent = Some(7);
+        tasks[1].parent = Some(6);
+        let rows: Vec<(usize, u32)> =
+            nest(tasks.clone()).into_iter().map(|(depth, task)| (depth, task.id)).collect();
+        assert_eq!(rows, vec![(0, 6), (1, 7)]);
+        assert_eq!(descendants_of(&tasks, 6), vec![7]);
+    }
+
+    #[test]
+    fn counts_done_subtasks() {
+        let mut tasks = tasks();
+        tasks[0].done = true;
+        let progress = progress(&tasks);
+        assert_eq!(progress.get(&1), Some(&(1, 2)));
+        assert_eq!(progress.get(&2), Some(&(0, 1)));
+        assert!(has_open_subtasks(&tasks, 1));
+        tasks[2].done = true;
+        assert!(!has_open_subtasks(&tasks, 1));
+    }
+
+    #[test]
+    fn cascades_to_every_level_below() {
+        let mut descendants = descendants_of(&tasks(), 1);
+        descendants.sort();
+        assert_eq!(descendants, vec![2, 3, 4]);
+        assert!(descendants_of(&tasks(), 4).is_empty());
+
+        // `kask complete --cascade` completes the task and everything below it
+        let mut completed = tasks();
+        let today = completed[0].date;
+        for id in [vec![1], descendants_of(&completed, 1)].concat() {
+            edit::complete_task(&mut completed, id, 10, today).unwrap();
+        }
+        completed.retain(|task| task.done);
+        assert_eq!(ids(&completed), vec![1, 2, 3, 4]);
+
+        let mut tasks = tasks();
+        let mut removed = remove_task(&mut tasks, 1, true).unwrap();
+        removed.sort();
+        assert_eq!(removed, vec![1, 2, 3, 4]);
+        assert_eq!(ids(&tasks), vec![5]);
+    }
+
+    #[test]
+    fn subtasks_of_a_removed_task_move_up() {
+        let mut tasks = tasks();
+        assert_eq!(remove_task(&mut tasks, 2, false), Ok(vec![2]));
+        assert_eq!(tasks.iter().find(|task| task.id == 4).unwrap().parent, Some(1));
+        assert_eq!(remove_task(&mut tasks, 1, false), Ok(vec![1]));
+        assert!(tasks.iter().filter(|task| task.id != 5).all(|task| task.parent.is_none()));
+        assert!(remove_task(&mut tasks, 1, false).is_err());
+    }
+
+    #[test]
+    fn refuses_to_move_a_task_under_its_own_subtask() {
+        let mut tasks = tasks();
+        assert!(set_parent(&mut tasks, 1, Some(4)).is_err());
+        assert!(set_parent(&mut tasks, 1, Some(1)).is_err());
+        assert!(set_parent(&mut tasks, 1, Some(8)).is_err());
+        set_parent(&mut tasks, 4, Some(3)).unwrap();
+        set_parent(&mut tasks, 3, None).unwrap();
+        assert_eq!(descendants_of(&tasks, 3), vec![4]);
+    }
+
+    #[test]
+    fn reads_only_the_subtasks_and_dependencies_of_the_shown_tasks() {
+        let mut tasks = tasks();
+        tasks[1].depends_on = vec![5];
+        tasks.push(testing::task(6, "unrelated", "2026-10-20"));
+        let dir = TempDir::new();
+        let csv = dir.path("tasks.csv");
+        let mut sqlite = SqliteStore::open(":memory:").unwrap();
+        sqlite.write_all(&tasks).unwrap();
+        store::open_store(&csv).unwrap().write_all(&tasks).unwrap();
+
+        // task 1 is shown, its subtasks and task 5 it waits for are needed too
+        let shown: Vec<Task> = tasks.iter().filter(|task| task.id == 1).cloned().collect();
+        assert_eq!(ids(&store::load_related(&sqlite, &shown).unwrap()), vec![1, 2, 3, 5]);
+        let csv_store = store::open_store(&csv).unwrap();
+        let related = store::load_related(csv_store.as_ref(), &shown).unwrap();
+        assert_eq!(ids(&related), vec![1, 2, 3, 5]);
+        assert!(store::load_related(&sqlite, &[]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::BTreeMap;
+
+use clap::{Args, Subcommand};
+use prettytable::Table;
+
+use crate::dates::DateSettings;
+use crate::query::{Field, Op, Query, Value};
+use crate::{edit, Task};
+
+#[derive(Subcommand, Debug)]
+pub enum TagCommand {
+    /// Add a tag to one or more tasks
+    Add {
+        tag: String,
+        #[clap(required_unless_present = "where_query")]
+        ids: Vec<u32>,
+        /// Also add it to every task that matches this query
+        #[clap(long = "where", value_name = "QUERY")]
+        where_query: Option<String>,
+    },
+    /// Remove a tag from one or more tasks
+    Remove {
+        tag: String,
+        #[clap(required_unless_present = "where_query")]
+        ids: Vec<u32>,
+        /// Also remove it from every task that matches this query
+        #[clap(long = "where", value_name = "QUERY")]
+        where_query: Option<String>,
+    },
+    /// Rename a tag on every task of the list
+    Rename { from: String, to: String },
+    /// Replace a tag with another one that may already be in use
+    Merge { from: String, into: String },
+    /// Show every tag with its number of open and done tasks
+    List {
+        /// Count the archived tasks as well
+        #[clap(long)]
+        archived: bool,
+    },
+}
+
+/// Tag filters shared by `kask list` and `kask search`.
+#[derive(Args, Debug, Clone, Default)]
+pub struct TagFilterArgs {
+    /// Only show tasks with this tag, or without it when prefixed with `!`.
+    /// Can be repeated and every one has to match
+    #[clap(long = "tag", alias = "tags", value_name = "TAG")]
+    pub tags: Vec<String>,
+    /// Only show tasks that have at least one of these comma separated tags
+    #[clap(long, value_delimiter = ',', value_name = "TAGS")]
+    pub any_tag: Vec<String>,
+}
+
+impl TagFilterArgs {
+    /// The query the tag filters stand for, `--tag work --tag !home --any-tag a,b`
+    /// is the same as `tag=work and tag!=home and (tag=a or tag=b)`.
+    pub fn into_query(self) -> Query {
+        let tag_is = |tag: String| Query::Compare(Field::Tag, Op::Eq, Value::Text(tag));
+
+        let mut query = Query::All;
+        for tag in self.tags {
+            query = query.and(match tag.trim().strip_prefix('!') {
+                Some(excluded) => tag_is(normalize_tag(excluded)).negate(),
+                None => tag_is(normalize_tag(&tag)),
+            });
+        }
+
+        let any_tag = normalize_tags(self.any_tag)
+            .into_iter()
+            .map(tag_is)
+            .reduce(Query::or);
+        match any_tag {
+            Some(any_tag) => query.and(any_tag),
+            None => query,
+        }
+    }
+}
+
+/// Tags are compared without the whitespace around them.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_string()
+}
+
+/// Trim the tags given on the command line, split the ones that contain the
+/// `;` separator used by the csv lists and drop the empty ones and the repeats.
+/// Tags are case sensitive, `Work` and `work` are two tags.
+pub fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    clean_tags(tags.iter().flat_map(|tag| tag.split(';')).map(String::from))
+}
+
+/// Trim `tags` and drop the empty ones and the repeats, the first of each is kept.
+pub fn clean_tags(tags: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = normalize_tag(&tag);
+        if !tag.is_empty() && !cleaned.contains(&tag) {
+            cleaned.push(tag);
+        }
+    }
+    cleaned
+}
+
+/// Whether the task tags contain `tag`, ignoring surrounding whitespace.
+pub fn has_tag(task_tags: &[String], tag: &str) -> bool {
+    task_tags.iter().any(|task_tag| task_tag.trim() == tag)
+}
+
+/// Set the tags of one task through `edit::edit_task` so they are validated
+/// and normalized the same way as `kask update --tags`.
+fn set_task_tags(
+    tasks: &mut [Task],
+    id: u32,
+    new_tags: Vec<String>,
+    settings: &DateSettings,
+) -> Result<(), String> {
+    edit::edit_task(
+        tasks,
+        id,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(new_tags),
+        None,
+        None,
+        settings,
+    )
+}
+
+/// Add `tag` to every task in `ids`. Returns how many tasks changed.
+pub fn add_tag(
+    tasks: &mut [Task],
+    ids: &[u32],
+    tag: &str,
+    settings: &DateSettings,
+) -> Result<usize, String> {
+    let tag = normalize_tag(tag);
+    if tag.is_empty() || tag.contains(';') {
+        return Err(format!("Invalid tag: '{}'", tag));
+    }
+
+    let mut changed = 0;
+    for id in ids.iter() {
+        let task = tasks
+            .iter()
+            .find(|task| task.id == *id)
+            .ok_or(format!("Task {} not found", id))?;
+        if has_tag(&task.tags, &tag) {
+            continue;
+        }
+        let mut new_tags = task.tags.clone();
+        new_tags.push(tag.clone());
+        set_task_tags(tasks, *id, new_tags, settings)?;
+        changed += 1;
+    }
+    Ok(changed)
+}
+
+/// Remove `tag` from every task in `ids`. Returns how many tasks changed.
+pub fn remove_tag(
+    tasks: &mut [Task],
+    ids: &[u32],
+    tag: &str,
+    settings: &DateSettings,
+) -> Result<usize, String> {
+    let tag = normalize_tag(tag);
+    let mut changed = 0;
+    for id in ids.iter() {
+        let task = tasks
+            .iter()
+            .find(|task| task.id == *id)
+            .ok_or(format!("Task {} not found", id))?;
+        if !has_tag(&task.tags, &tag) {
+            continue;
+        }
+        let new_tags = task
+            .tags
+            .iter()
+            .filter(|task_tag| task_tag.trim() != tag)
+            .cloned()
+            .collect();
+        set_task_tags(tasks, *id, new_tags, settings)?;
+        changed += 1;
+    }
+    Ok(changed)
+}
+
+/// Replace `from` with `into` on every task of the list, a task that already
+/// has `into` keeps a single copy of it. Returns how many tasks changed.
+pub fn merge_tags(
+    tasks: &mut [Task],
+    from: &str,
+    into: &str,
+    settings: &DateSettings,
+) -> Result<usize, String> {
+    let from = normalize_tag(from);
+    let into = normalize_tag(into);
+    if into.is_empty() || into.contains(';') {
+        return Err(format!("Invalid tag: '{}'", into));
+    }
+
+    let ids: Vec<u32> = tasks
+        .iter()
+        .filter(|task| has_tag(&task.tags, &from))
+        .map(|task| task.id)
+        .collect();
+    for id in ids.iter() {
+        let task = tasks.iter().find(|task| task.id == *id).unwrap();
+        let mut new_tags: Vec<String> = Vec::new();
+        for tag in task.tags.iter() {
+            let tag = if tag.trim() == from { into.clone() } else { normalize_tag(tag) };
+            if !new_tags.contains(&tag) {
+                new_tags.push(tag);
+            }
+        }
+        set_task_tags(tasks, *id, new_tags, settings)?;
+    }
+    Ok(ids.len())
+}
+
+/// Rename a tag across the whole list. Use `merge_tags` when the new name is
+/// already in use.
+pub fn rename_tag(
+    tasks: &mut [Task],
+    from: &str,
+    to: &str,
+    settings: &DateSettings,
+) -> Result<usize, String> {
+    if tasks.iter().any(|task| has_tag(&task.tags, &normalize_tag(to))) {
+        return Err(format!(
+            "Tag {} is already in use, use `kask tag merge {} {}` to combine them",
+            to.trim(),
+            from.trim(),
+            to.trim()
+        ));
+    }
+    merge_tags(tasks, from, to, settings)
+}
+
+/// Print every tag with the number of open and done tasks that use it.
+pub fn print_tag_counts(tasks: &[Task], title: &str) {
+    let mut counts: BTreeMap<String, (u32, u32)> = BTreeMap::new();
+    for task in tasks.iter() {
+        for tag in task.tags.iter() {
+            let count = counts.entry(normalize_tag(tag)).or_default();
+            if task.done {
+                count.1 += 1;
+            } else {
+                count.0 += 1;
+            }
+        }
+    }
+
+    let mut table = Table::new();
+    println!("\n");
+    println!("{}:", title);
+    table.set_titles(row!["Tag", "Open", "Done"]);
+    for (tag, (open, done)) in counts {
+        table.add_row(row![tag, open, done]);
+    }
+    table.printstd();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn tags(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn tasks() -> Vec<Task> {
+        let mut tasks: Vec<Task> = (1..=3)
+            .map(|id| testing::task(id, &format!("task {}", id), "2026-10-20"))
+            .collect();
+        tasks[0].tags = tags(&["work", "home"]);
+        tasks[1].tags = tags(&[" work ", "job"]);
+        tasks[2].tags = tags(&["Work"]);
+        tasks
+    }
+
+    #[test]
+    fn normalizing_trims_splits_and_drops_repeats() {
+        assert_eq!(
+            normalize_tags(tags(&[" work", "work ", "a; b;", "", " ; ", "Work", "b"])),
+            tags(&["work", "a", "b", "Work"])
+        );
+        assert_eq!(clean_tags(tags(&[" a;b ", "a;b", "c"])), tags(&["a;b", "c"]));
+    }
+
+    #[test]
+    fn renames_a_tag_whatever_the_whitespace_around_it() {
+        let settings = DateSettings::default();
+        let mut tasks = tasks();
+        assert_eq!(rename_tag(&mut tasks, " work", "office ", &settings), Ok(2));
+        assert_eq!(tasks[0].tags, tags(&["office", "home"]));
+        assert_eq!(tasks[1].tags, tags(&["office", "job"]));
+        // tags are case sensitive
+        assert_eq!(tasks[2].tags, tags(&["Work"]));
+    }
+
+    #[test]
+    fn renaming_onto_a_tag_in_use_asks_for_a_merge() {
+        let settings = DateSettings::default();
+        let mut tasks = tasks();
+        let error = rename_tag(&mut tasks, "work", " job", &settings).unwrap_err();
+        assert!(error.contains("kask tag merge work job"), "{}", error);
+        assert_eq!(tasks[1].tags, tags(&[" work ", "job"]));
+    }
+
+    #[test]
+    fn merging_keeps_a_single_copy() {
+        let settings = DateSettings::default();
+        let mut tasks = tasks();
+        assert_eq!(merge_tags(&mut tasks, "work", "job", &settings), Ok(2));
+        assert_eq!(tasks[0].tags, tags(&["job", "home"]));
+        assert_eq!(tasks[1].tags, tags(&["job"]));
+        assert_eq!(tasks[2].tags, tags(&["Work"]));
+        assert!(merge_tags(&mut tasks, "job", "a;b", &settings).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+//! Helpers shared by the unit tests.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chrono::NaiveDate;
+
+use crate::priority::Priority;
+use crate::Task;
+
+/// A directory in the temp directory that is removed again when dropped, so
+/// the lists a test writes and the files kept next to them go away together.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> TempDir {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!(
+            "kask-test-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    /// Path of `name` inside the directory.
+    pub fn path(&self, name: &str) -> String {
+        self.0.join(name).to_string_lossy().to_string()
+    }
+
+    /// Write `contents` to `name` and return its path.
+    pub fn file(&self, name: &str, contents: &str) -> String {
+        let path = self.path(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// An open task without a time, due on `date` given as yyyy-mm-dd.
+pub fn task(id: u32, name: &str, date: &str) -> Task {
+    Task {
+        id,
+        name: name.to_string(),
+        date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+        time: None,
+        description: String::new(),
+        done: false,
+        tags: Vec::new(),
+        priority: Priority::None,
+        recurrence: None,
+        parent: None,
+        depends_on: Vec::new(),
+    }
+}
@@ -0,0 +1,223 @@
+//! Deleted tasks, kept in a `<list file>.trash` file next to the list with one
+//! json entry per line until `kask trash empty` removes them for good.
+
+use std::fs;
+use std::io::Write;
+
+use chrono::{Days, NaiveDateTime};
+use clap::Subcommand;
+use prettytable::Table;
+use serde::{Deserialize, Serialize};
+
+use crate::dates::DateSettings;
+use crate::Task;
+
+#[derive(Subcommand, Debug)]
+pub enum TrashCommand {
+    /// Show the deleted tasks of the current list
+    List {},
+    /// Move a deleted task back to the current list
+    Restore { id: u32 },
+    /// Remove the deleted tasks for good
+    Empty {
+        /// Only remove the tasks deleted longer ago than this, for example 30d or 2w
+        #[clap(long, value_name = "AGE")]
+        older_than: Option<String>,
+        /// Do not ask for confirmation
+        #[clap(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TrashedTask {
+    pub deleted: NaiveDateTime,
+    pub task: Task,
+}
+
+pub fn trash_path(list_path: &str) -> String {
+    format!("{}.trash", list_path)
+}
+
+pub fn load(path: &str) -> Result<Vec<TrashedTask>, String> {
+    if !std::path::Path::new(path).exists() {
+        return Ok(Vec::new());
+    }
+    let contents = fs::read_to_string(path).map_err(|e| format!("Error reading file: {}", e))?;
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| format!("Error parsing line {} of {}: {}", index + 1, path, e))
+        })
+        .collect()
+}
+
+fn write(path: &str, trashed: &[TrashedTask]) -> Result<(), String> {
+    let mut contents = String::new();
+    for entry in trashed.iter() {
+        contents.push_str(&serde_json::to_string(entry).map_err(|e| e.to_string())?);
+        contents.push('\n');
+    }
+    fs::write(path, contents).map_err(|e| format!("Error writing to file: {}", e))
+}
+
+/// Put deleted tasks in the trash.
+pub fn add(path: &str, tasks: Vec<Task>, deleted: NaiveDateTime) -> Result<(), String> {
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .map_err(|e| format!("Error opening file: {}", e))?;
+    for task in tasks {
+        let line = serde_json::to_string(&TrashedTask { deleted, task })
+            .map_err(|e| e.to_string())?;
+        writeln!(file, "{}", line).map_err(|e| format!("Error writing to file: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Take the task with `id` out of the trash.
+pub fn take(path: &str, id: u32) -> Result<Task, String> {
+    let mut trashed = load(path)?;
+    let position = trashed
+        .iter()
+        .rposition(|entry| entry.task.id == id)
+        .ok_or(format!("Task {} is not in the trash", id))?;
+    let entry = trashed.remove(position);
+    write(path, &trashed)?;
+    Ok(entry.task)
+}
+
+/// Remove the tasks deleted before `before`, or every task when it is `None`.
+/// Returns how many were removed.
+pub fn empty(path: &str, before: Option<NaiveDateTime>) -> Result<usize, String> {
+    let mut trashed = load(path)?;
+    let count = trashed.len();
+    trashed.retain(|entry| before.is_some_and(|before| entry.deleted >= before));
+    write(path, &trashed)?;
+    Ok(count - trashed.len())
+}
+
+/// Drop the tasks that are back in the list unchanged, for example after
+/// `kask undo` put a deleted task back.
+pub fn remove_restored(path: &str, tasks: &[Task]) -> Result<(), String> {
+    let mut trashed = load(path)?;
+    let count = trashed.len();
+    trashed.retain(|entry| !tasks.contains(&entry.task));
+    if trashed.len() != count {
+        write(path, &trashed)?;
+    }
+    Ok(())
+}
+
+/// The time an age such as `30d`, `2w` or `30`, which is in days, before `now`.
+pub fn age_cutoff(value: &str, now: NaiveDateTime) -> Result<NaiveDateTime, String> {
+    let value = value.trim().to_lowercase();
+    let invalid =
+        || format!("Invalid age: {} (expected a number of days such as 30d or 2w)", value);
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value.as_str(), "d"),
+    };
+    let number = number.parse::<u64>().map_err(|_| invalid())?;
+    let days = match unit.trim() {
+        "d" | "day" | "days" => Some(number),
+        "w" | "week" | "weeks" => number.checked_mul(7),
+        _ => None,
+    };
+    days.and_then(|days| now.checked_sub_days(Days::new(days))).ok_or_else(invalid)
+}
+
+pub fn print_trash(trashed: &[TrashedTask], title: &str, settings: &DateSettings) {
+    let mut table = Table::new();
+    println!("\n");
+    println!("{}:", title);
+    table.set_titles(row!["ID", "Name", "Date", "Done", "Deleted"]);
+    for entry in trashed.iter() {
+        let deleted = format!(
+            "{} {}",
+            settings.format_date(&entry.deleted.date()),
+            settings.format_time(&Some(entry.deleted.time()))
+        );
+        table.add_row(row![
+            entry.task.id,
+            entry.task.name,
+            settings.format_date(&entry.task.date),
+            entry.task.done,
+            deleted
+        ]);
+    }
+    table.printstd();
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    fn ids(path: &str) -> Vec<u32> {
+        load(path).unwrap().iter().map(|entry| entry.task.id).collect()
+    }
+
+    #[test]
+    fn takes_the_last_copy_of_a_task() {
+        let dir = TempDir::new();
+        let path = dir.path("tasks.csv.trash");
+        let mut again = testing::task(1, "deleted again", "2026-10-20");
+        again.done = true;
+        add(&path, vec![testing::task(1, "deleted", "2026-10-20")], at(1, 9)).unwrap();
+        add(&path, vec![testing::task(2, "other", "2026-10-21")], at(2, 9)).unwrap();
+        add(&path, vec![again.clone()], at(3, 9)).unwrap();
+
+        assert_eq!(take(&path, 1), Ok(again));
+        assert_eq!(ids(&path), vec![1, 2]);
+        assert!(take(&path, 3).is_err());
+        assert_eq!(ids(&path), vec![1, 2]);
+    }
+
+    #[test]
+    fn empties_the_tasks_deleted_before_the_cutoff() {
+        let dir = TempDir::new();
+        let path = dir.path("tasks.csv.trash");
+        assert_eq!(empty(&path, None), Ok(0));
+        add(&path, vec![testing::task(1, "old", "2026-10-01")], at(1, 9)).unwrap();
+        add(&path, vec![testing::task(2, "recent", "2026-10-10")], at(10, 9)).unwrap();
+        add(&path, vec![testing::task(3, "new", "2026-10-17")], at(17, 9)).unwrap();
+
+        let cutoff = age_cutoff("7d", at(18, 9)).unwrap();
+        assert_eq!(cutoff, at(11, 9));
+        assert_eq!(empty(&path, Some(cutoff)), Ok(2));
+        assert_eq!(ids(&path), vec![3]);
+        assert_eq!(empty(&path, None), Ok(1));
+        assert!(ids(&path).is_empty());
+    }
+
+    #[test]
+    fn reads_ages_in_days_and_weeks() {
+        let now = at(18, 9);
+        assert_eq!(age_cutoff("30", now), Ok(now - Days::new(30)));
+        assert_eq!(age_cutoff("2w", now), Ok(at(4, 9)));
+        assert_eq!(age_cutoff(" 3 Days ", now), Ok(at(15, 9)));
+        assert_eq!(age_cutoff("0d", now), Ok(now));
+        for age in ["", "d", "-1d", "2m", "1.5w", "99999999999999999999d"] {
+            assert!(age_cutoff(age, now).is_err(), "{} should not parse", age);
+        }
+    }
+
+    #[test]
+    fn ages_before_the_earliest_date_are_errors() {
+        let now = at(18, 9);
+        assert!(age_cutoff("4000000000d", now).is_err());
+        assert!(age_cutoff("3000000000000000000w", now).is_err());
+        assert!(age_cutoff("18446744073709551615d", now).is_err());
+    }
+}
@@ -4,103 +4,293 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::{env, fs};
 
-use crate::{KaskConfig, Task, CONFIG_FILE_ENV_VAR};
+use chrono::{Datelike, NaiveDate, NaiveTime};
 
-pub fn parse_tags(tags: Option<Vec<String>>) -> String {
-    match tags {
-        Some(tags) => tags.join("; "),
-        None => String::from(""),
+use crate::priority::Priority;
+use crate::recurrence;
+use crate::{csv, KaskConfig, Task, CONFIG_FILE_ENV_VAR, DATE_FORMAT, TIME_FORMAT};
+
+/// Parse a date in the format used by the task list files.
+pub fn parse_stored_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value.trim(), DATE_FORMAT)
+        .map_err(|_| format!("Invalid date: {} (expected mm/dd/yy)", value.trim()))
+}
+
+/// Parse a time in the format used by the task list files, an empty value
+/// means the task has no time.
+pub fn parse_stored_time(value: &str) -> Result<Option<NaiveTime>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
     }
+    NaiveTime::parse_from_str(value, TIME_FORMAT)
+        .map(Some)
+        .map_err(|_| format!("Invalid time: {} (expected hh:mm[am|pm])", value))
 }
 
-pub fn write_tasks_to_file(filename: &str, tasks: Vec<Task>) {
-    let mut file = fs::OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(filename)
-        .unwrap();
-
-    for task in tasks {
-        let store_string: String = format!(
-            "{}, {}, {}, {}, {}, {}, {}",
-            task.id,
-            task.name.trim().to_string(),
-            task.date.trim().to_string(),
-            task.time.trim().to_string(),
-            task.description.trim().to_string(),
-            task.done.to_string(),
-            task.tags.join("; ")
-        );
+/// Parse the parent id of a task, an empty value means a top level task.
+pub fn parse_parent(value: &str) -> Result<Option<u32>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse::<u32>()
+        .map(Some)
+        .map_err(|_| format!("Invalid parent id: {}", value))
+}
 
-        if let Err(e) = writeln!(file, "{}", store_string) {
-            eprintln!("Error writing to file: {}", e);
-        };
+/// Parse the `;` separated ids a task depends on.
+pub fn parse_depends_on(value: &str) -> Result<Vec<u32>, String> {
+    value
+        .split(';')
+        .map(|id| id.trim())
+        .filter(|id| !id.is_empty())
+        .map(|id| id.parse::<u32>().map_err(|_| format!("Invalid dependency id: {}", id)))
+        .collect()
+}
+
+/// Format a date for the csv lists. Their two digit years read back as 1969
+/// to 2068, so dates outside of that are refused instead of being changed.
+pub fn format_stored_date(date: &NaiveDate) -> Result<String, String> {
+    if !(1969..=2068).contains(&date.year()) {
+        return Err(format!(
+            "The csv task lists can only hold dates from 1969 to 2068, not {}. Use \
+             `kask config migrate` to move the list to json or sqlite",
+            date.format("%Y-%m-%d")
+        ));
     }
+    Ok(date.format(DATE_FORMAT).to_string())
 }
 
-pub fn append_task_to_file(task: Task, filename: &str) {
-    let store_string: String = format!(
-        "{}, {}, {}, {}, {}, {}, {}",
-        task.id,
-        task.name,
-        task.date,
-        task.time,
-        task.description,
+pub fn format_stored_time(time: &Option<NaiveTime>) -> String {
+    match time {
+        Some(time) => time.format(TIME_FORMAT).to_string(),
+        None => String::new(),
+    }
+}
+
+/// Serde helpers for the json lists. Dates are written as yyyy-mm-dd so every
+/// year survives, the mm/dd/yy dates of older json lists are still read.
+pub mod serde_date {
+    use chrono::NaiveDate;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub const JSON_DATE_FORMAT: &str = "%Y-%m-%d";
+
+    pub fn serialize<S: Serializer>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&date.format(JSON_DATE_FORMAT).to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        parse(&value).map_err(serde::de::Error::custom)
+    }
+
+    /// Parse a date as stored in a json list.
+    pub fn parse(value: &str) -> Result<NaiveDate, String> {
+        NaiveDate::parse_from_str(value.trim(), JSON_DATE_FORMAT)
+            .or_else(|_| super::parse_stored_date(value))
+            .map_err(|_| format!("Invalid date: {} (expected yyyy-mm-dd)", value.trim()))
+    }
+}
+
+pub mod serde_time {
+    use chrono::NaiveTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        time: &Option<NaiveTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::format_stored_time(time))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<NaiveTime>, D::Error> {
+        let value = Option::<String>::deserialize(deserializer)?.unwrap_or_default();
+        super::parse_stored_time(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Header row written at the top of every task list file. Files without a
+/// header are treated as the legacy `", "` separated format.
+pub const TASK_FILE_HEADER: [&str; 11] = [
+    "id",
+    "name",
+    "date",
+    "time",
+    "description",
+    "done",
+    "tags",
+    "priority",
+    "recurrence",
+    "parent",
+    "depends_on",
+];
+
+/// Columns of the legacy format, in the order they are written.
+pub const LEGACY_TASK_FILE_COLUMNS: [&str; 7] =
+    ["id", "name", "date", "time", "description", "done", "tags"];
+
+fn task_to_record(task: &Task) -> Result<Vec<String>, String> {
+    Ok(vec![
+        task.id.to_string(),
+        task.name.trim().to_string(),
+        format_stored_date(&task.date)?,
+        format_stored_time(&task.time),
+        task.description.trim().to_string(),
         task.done.to_string(),
-        task.tags.join("; ")
-    );
+        task.tags.join("; "),
+        match task.priority {
+            Priority::None => String::new(),
+            priority => priority.to_string(),
+        },
+        task.recurrence.as_ref().map(|recurrence| recurrence.to_string()).unwrap_or_default(),
+        task.parent.map(|parent| parent.to_string()).unwrap_or_default(),
+        task.depends_on.iter().map(|id| id.to_string()).collect::<Vec<String>>().join("; "),
+    ])
+}
+
+fn task_from_record(header: &[String], record: &[String]) -> Result<Task, String> {
+    let field = |name: &str| -> Result<&str, String> {
+        let index = header
+            .iter()
+            .position(|column| column == name)
+            .ok_or(format!("Missing column {}", name))?;
+        record
+            .get(index)
+            .map(|value| value.as_str())
+            .ok_or(format!("Missing value for column {}", name))
+    };
+
+    if record.len() != header.len() {
+        return Err(format!(
+            "Invalid number of fields in task record: expected {}, found {}",
+            header.len(),
+            record.len()
+        ));
+    }
+
+    let tags = field("tags")?;
+    Ok(Task {
+        id: field("id")?.trim().parse::<u32>().map_err(|e| e.to_string())?,
+        name: field("name")?.to_string(),
+        date: parse_stored_date(field("date")?)?,
+        time: parse_stored_time(field("time")?)?,
+        description: field("description")?.to_string(),
+        done: field("done")?.trim().parse::<bool>().map_err(|e| e.to_string())?,
+        tags: if tags.trim().is_empty() {
+            Vec::new()
+        } else {
+            tags.split(';').map(|tag| tag.trim().to_string()).collect()
+        },
+        // files written before these were added do not have their columns
+        priority: field("priority").unwrap_or("").parse::<Priority>()?,
+        recurrence: recurrence::parse_recurrence(field("recurrence").unwrap_or(""))?,
+        parent: parse_parent(field("parent").unwrap_or(""))?,
+        depends_on: parse_depends_on(field("depends_on").unwrap_or(""))?,
+    })
+}
+
+/// Legacy files start right away with a task, every other file starts with a
+/// header row.
+pub fn is_legacy_task_file(contents: &str) -> bool {
+    let first_line = contents.lines().next().unwrap_or("").trim();
+    !first_line.is_empty() && first_line.split(',').next() != Some("id")
+}
+
+/// Whether the file is empty or has exactly the header this version writes.
+fn has_current_header(contents: &str) -> bool {
+    let first_line = contents.lines().next().unwrap_or("").trim();
+    first_line.is_empty() || first_line == TASK_FILE_HEADER.join(",")
+}
+
+pub fn write_tasks_to_file(filename: &str, tasks: &[Task]) -> Result<(), String> {
+    let mut contents = csv::format_record(&TASK_FILE_HEADER);
+    for task in tasks.iter() {
+        contents.push_str(&csv::format_record(&task_to_record(task)?));
+    }
+
+    fs::write(filename, contents).map_err(|e| format!("Error writing to file: {}", e))
+}
+
+pub fn append_task_to_file(task: &Task, filename: &str) -> Result<(), String> {
+    let existing = fs::read_to_string(filename).unwrap_or_default();
+
+    // files in an older format are rewritten in full so the list never mixes
+    // two formats
+    if is_legacy_task_file(&existing) || !has_current_header(&existing) {
+        let mut tasks = load_tasks_from_file(filename)?;
+        tasks.push(task.clone());
+        return write_tasks_to_file(filename, &tasks);
+    }
+
+    let mut store_string = String::new();
+    if existing.trim().is_empty() {
+        store_string.push_str(&csv::format_record(&TASK_FILE_HEADER));
+    }
+    store_string.push_str(&csv::format_record(&task_to_record(task)?));
 
     // if the file does not exist create it
     let mut file = fs::OpenOptions::new()
-        .write(true)
         .append(true)
         .create(true)
         .open(filename)
-        .unwrap();
+        .map_err(|e| format!("Error opening file: {}", e))?;
 
-    if let Err(e) = writeln!(file, "{}", store_string) {
-        eprintln!("Error writing to file: {}", e);
-    };
+    file.write_all(store_string.as_bytes())
+        .map_err(|e| format!("Error writing to file: {}", e))
 }
 
-pub fn load_tasks_from_file(filename: &str) -> Vec<Task> {
+pub fn load_tasks_from_file(filename: &str) -> Result<Vec<Task>, String> {
     let mut tasks: Vec<Task> = Vec::new();
 
     // if the file does not exist create it
-
     if !PathBuf::from(filename).exists() {
-        let mut file = fs::OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(filename)
-            .unwrap();
+        fs::write(filename, csv::format_record(&TASK_FILE_HEADER))
+            .map_err(|e| format!("Error creating file: {}", e))?;
         println!("new tasklist file created at {}", filename);
-        return tasks;
+        return Ok(tasks);
     }
 
-    let file = fs::read_to_string(filename);
-    if file.is_err() {
-        eprintln!("Error reading file: {}", file.unwrap_err());
-        return tasks;
-    }
+    let file = fs::read_to_string(filename).map_err(|e| format!("Error reading file: {}", e))?;
 
-    let file = file.unwrap();
-    for line in file.lines() {
-        let task = Task::from_str(line);
-        if task.is_err() {
-            eprintln!("Error parsing task: {}", task.unwrap_err());
-            continue;
+    if is_legacy_task_file(&file) {
+        for (number, line) in file.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let task = Task::from_str(line)
+                .map_err(|e| format!("Error parsing line {} of {}: {}", number + 1, filename, e))?;
+            tasks.push(task);
         }
-        tasks.push(task.unwrap());
+        return Ok(tasks);
     }
 
-    tasks
+    let records =
+        csv::parse_records(&file).map_err(|e| format!("Error parsing file {}: {}", filename, e))?;
+
+    let mut records = records.into_iter();
+    let header = match records.next() {
+        Some(header) => header,
+        None => return Ok(tasks),
+    };
+
+    for (number, record) in records.enumerate() {
+        // the header is record 1
+        let task = task_from_record(&header, &record)
+            .map_err(|e| format!("Error parsing record {} of {}: {}", number + 2, filename, e))?;
+        tasks.push(task);
+    }
+
+    Ok(tasks)
 }
 
-fn new_kask_config() -> KaskConfig {
+fn new_kask_config(data_dir: &Path) -> KaskConfig {
     let mut tasks_lists_paths: HashMap<String, String> = HashMap::new();
-    let path_prefix = Path::new(".").canonicalize().unwrap();
-    let filepath = path_prefix.join("default_tasks.csv");
+    let filepath = data_dir.join("default_tasks.csv");
     tasks_lists_paths.insert(
         "default_tasks".to_string(),
         filepath.to_str().unwrap().to_string(),
@@ -108,25 +298,94 @@ fn new_kask_config() -> KaskConfig {
     KaskConfig {
         current_tasks_list: "default_tasks".to_string(),
         tasks_lists_paths,
+        ..KaskConfig::default()
     }
 }
 
-pub fn get_kask_config_file() -> Option<KaskConfig> {
-    let config_file_path = get_config_file_path();
-    if config_file_path.is_none() {
-        return None;
+/// Read `name` from the environment, treating an empty value as unset.
+fn env_path(name: &str) -> Option<PathBuf> {
+    env::var(name).ok().filter(|value| !value.is_empty()).map(PathBuf::from)
+}
+
+/// Where `kask init` writes the config: the file named by the environment
+/// variable if set, otherwise `$XDG_CONFIG_HOME/kask/kask.config`.
+pub fn default_config_file_path() -> Result<PathBuf, String> {
+    if let Some(path) = env_path(CONFIG_FILE_ENV_VAR) {
+        return Ok(path);
     }
+    let config_home = env_path("XDG_CONFIG_HOME")
+        .or_else(|| env_path("HOME").map(|home| home.join(".config")))
+        .ok_or("Could not find the config directory, set HOME or XDG_CONFIG_HOME")?;
+    Ok(config_home.join("kask").join("kask.config"))
+}
+
+/// Directory the default task list goes in, `$XDG_DATA_HOME/kask`.
+pub fn default_data_dir() -> Result<PathBuf, String> {
+    let data_home = env_path("XDG_DATA_HOME")
+        .or_else(|| env_path("HOME").map(|home| home.join(".local").join("share")))
+        .ok_or("Could not find the data directory, set HOME or XDG_DATA_HOME")?;
+    Ok(data_home.join("kask"))
+}
+
+/// Write a config with a single default list and create that list. Refuses to
+/// replace an existing config unless `force` is set.
+pub fn init_kask_config(force: bool) -> Result<KaskConfig, String> {
+    let config_path = default_config_file_path()?;
+    if config_path.exists() && !force {
+        return Err(format!(
+            "Configuration file {} already exists, use --force to replace it",
+            config_path.display()
+        ));
+    }
+
+    let data_dir = default_data_dir()?;
+    let config = new_kask_config(&data_dir);
 
-    let config_file_path = config_file_path.unwrap();
-    let config_file = fs::read_to_string(&config_file_path);
-    if config_file.is_err() {
-        eprintln!("Error reading file: {}", config_file.unwrap_err());
-        return None;
+    if let Some(config_dir) = config_path.parent() {
+        fs::create_dir_all(config_dir)
+            .map_err(|e| format!("Error creating {}: {}", config_dir.display(), e))?;
     }
+    fs::create_dir_all(&data_dir).map_err(|e| format!("Error creating {}: {}", data_dir.display(), e))?;
 
-    let config_file = config_file.unwrap();
-    let config: KaskConfig = serde_json::from_str(&config_file).unwrap();
-    Some(config)
+    let config_json_string = serde_json::to_string(&config).map_err(|e| e.to_string())?;
+    fs::write(&config_path, config_json_string)
+        .map_err(|e| format!("Error writing {}: {}", config_path.display(), e))?;
+    println!("Configuration file created at {}", config_path.display());
+
+    let list_path = &config.tasks_lists_paths[&config.current_tasks_list];
+    if Path::new(list_path).exists() {
+        println!("Using the existing task list {} at {}", config.current_tasks_list, list_path);
+    } else {
+        write_tasks_to_file(list_path, &[])?;
+        println!("Task list {} created at {}", config.current_tasks_list, list_path);
+    }
+
+    Ok(config)
+}
+
+/// Ask a yes or no question on the terminal, anything but y or yes is a no.
+pub fn confirm(question: &str) -> Result<bool, String> {
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush().map_err(|e| e.to_string())?;
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .map_err(|e| format!("Error reading the answer: {}", e))?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Load the config, `None` if there is no config file anywhere.
+pub fn get_kask_config_file() -> Result<Option<KaskConfig>, String> {
+    let config_file_path = match get_config_file_path() {
+        Some(config_file_path) => config_file_path,
+        None => return Ok(None),
+    };
+    let config_file = fs::read_to_string(&config_file_path)
+        .map_err(|e| format!("Error reading file {}: {}", config_file_path, e))?;
+
+    serde_json::from_str(&config_file)
+        .map(Some)
+        .map_err(|e| format!("Error parsing configuration file {}: {}", config_file_path, e))
 }
 
 pub fn write_config_to_file(config: KaskConfig) -> Result<(), std::io::Error> {
@@ -135,24 +394,105 @@ pub fn write_config_to_file(config: KaskConfig) -> Result<(), std::io::Error> {
 }
 
 pub fn get_config_file_path() -> Option<String> {
-    // search for the environment variable
-    let env_config_file = env::var(CONFIG_FILE_ENV_VAR);
-
-    // if env variable found then load it and return it
-    if let Ok(path_from_var) = env_config_file {
-        return Some(Path::new(&path_from_var).canonicalize().unwrap().to_str().unwrap().to_string());
+    // if the environment variable is set then that is the config file
+    if let Some(path_from_var) = env_path(CONFIG_FILE_ENV_VAR) {
+        return path_from_var
+            .canonicalize()
+            .ok()
+            .map(|path| path.to_str().unwrap().to_string());
     };
 
-    // if env variable not found then search for a file in a folder ~/.config/kask/kask.config
-    let file_path: String = format!("{}/.config/kask/kask.config", env::var("HOME").unwrap());
-    if fs::metadata(&file_path).is_ok() {
-        return Some(file_path);
-    };
+    // otherwise search for a file in $XDG_CONFIG_HOME/kask/kask.config, which
+    // defaults to ~/.config/kask/kask.config
+    if let Ok(file_path) = default_config_file_path() {
+        if file_path.exists() {
+            return Some(file_path.to_str().unwrap().to_string());
+        }
+    }
+    if let Some(home) = env_path("HOME") {
+        let file_path = home.join(".config").join("kask").join("kask.config");
+        if file_path.exists() {
+            return Some(file_path.to_str().unwrap().to_string());
+        }
+    }
 
     // if that file is not found then search for a local file named kask.config
     if fs::metadata("kask.config").is_ok() {
         return Some("kask.config".to_string());
     };
 
-    return None;
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn loads_legacy_files() {
+        let contents = "1, buy milk, 10/20/26, 09:00am, from the shop, false, home; errands\n\n\
+                        2, call bob, 01/02/27, , , true, \n";
+        assert!(is_legacy_task_file(contents));
+        let dir = TempDir::new();
+        let path = dir.file("legacy.csv", contents);
+
+        let tasks = load_tasks_from_file(&path).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].name, "buy milk");
+        assert_eq!(tasks[0].date, date(2026, 10, 20));
+        assert_eq!(tasks[0].time, NaiveTime::from_hms_opt(9, 0, 0));
+        assert_eq!(tasks[0].tags, vec!["home", "errands"]);
+        assert!(!tasks[0].done);
+        assert_eq!(tasks[1].time, None);
+        assert!(tasks[1].tags.is_empty());
+        assert!(tasks[1].done);
+    }
+
+    #[test]
+    fn loads_files_from_before_the_newer_columns() {
+        let dir = TempDir::new();
+        let path = dir.file(
+            "old-header.csv",
+            "id,name,date,time,description,done,tags\n3,\"a, b\",10/20/26,,,false,x\n",
+        );
+        let tasks = load_tasks_from_file(&path).unwrap();
+        assert_eq!(tasks[0].name, "a, b");
+        assert_eq!(tasks[0].priority, Priority::None);
+        assert_eq!(tasks[0].recurrence, None);
+        assert_eq!(tasks[0].parent, None);
+        assert!(tasks[0].depends_on.is_empty());
+    }
+
+    #[test]
+    fn tasks_round_trip_through_a_csv_file() {
+        let task = Task {
+            id: 7,
+            name: String::from("Hello, \"world\""),
+            date: date(2068, 12, 31),
+            time: NaiveTime::from_hms_opt(23, 15, 0),
+            description: String::from("multi\nline, desc"),
+            done: false,
+            tags: vec![String::from("with space"), String::from("b")],
+            priority: Priority::High,
+            recurrence: recurrence::parse_recurrence("monthly on the last fri").unwrap(),
+            parent: Some(2),
+            depends_on: vec![3, 4],
+        };
+        let dir = TempDir::new();
+        let path = dir.path("round-trip.csv");
+        write_tasks_to_file(&path, std::slice::from_ref(&task)).unwrap();
+        assert_eq!(load_tasks_from_file(&path).unwrap(), vec![task]);
+    }
+
+    #[test]
+    fn refuses_years_two_digits_can_not_hold() {
+        assert_eq!(format_stored_date(&date(1969, 1, 1)).unwrap(), "01/01/69");
+        assert!(format_stored_date(&date(2069, 1, 1)).is_err());
+        assert!(format_stored_date(&date(1968, 12, 31)).is_err());
+    }
 }
@@ -0,0 +1,177 @@
+//! Named views saved in the config. A view bundles a `--where` filter, a sort
+//! order, a column set and a count so a `kask list` combination can be run
+//! again with `kask view <name>`.
+
+use clap::{Subcommand, ValueEnum};
+use prettytable::Table;
+use serde::{Deserialize, Serialize};
+
+use crate::dates::DateSettings;
+use crate::list::{self, Column, SortKey};
+use crate::query::Query;
+use crate::store;
+
+/// Names taken by the `kask view` subcommands, a view with one of them could
+/// never be shown.
+const RESERVED_NAMES: [&str; 3] = ["save", "list", "remove"];
+
+/// Number of tasks a view shows when it was saved without a count.
+const DEFAULT_COUNT: u32 = 10;
+
+#[derive(Subcommand, Debug)]
+pub enum ViewCommand {
+    /// Save a view to the configuration file
+    Save {
+        name: String,
+        /// Tasks to show, see `kask list --where`. Every task is shown by default
+        #[clap(long = "where", value_name = "QUERY")]
+        where_query: Option<String>,
+        /// Comma separated keys to sort by, the default sort of `kask list`
+        /// is used otherwise
+        #[clap(long, value_enum, value_delimiter = ',')]
+        sort: Vec<SortKey>,
+        /// Reverse the sort order
+        #[clap(long)]
+        reverse: bool,
+        /// Comma separated columns to show, id,name,date,time,status,repeats,
+        /// priority,done by default
+        #[clap(long, value_enum, value_delimiter = ',')]
+        columns: Vec<Column>,
+        /// Number of tasks to show from each list
+        #[clap(short, long)]
+        count: Option<u32>,
+        /// Comma separated lists to show, the current list by default
+        #[clap(long, value_delimiter = ',', conflicts_with = "all_lists")]
+        lists: Vec<String>,
+        /// Show every list in the configuration file
+        #[clap(long)]
+        all_lists: bool,
+        /// Replace the view if it already exists
+        #[clap(long)]
+        force: bool,
+    },
+    /// Show the saved views
+    List {},
+    /// Remove a saved view
+    Remove { name: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct View {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sort: Vec<SortKey>,
+    #[serde(default)]
+    pub reverse: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<Column>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+    /// Lists the view shows, empty for whichever list is current
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lists: Vec<String>,
+    #[serde(default)]
+    pub all_lists: bool,
+}
+
+impl View {
+    /// Check everything that can be wrong with a view before it is saved.
+    pub fn validate(
+        &self,
+        name: &str,
+        known_lists: &[&String],
+        settings: &DateSettings,
+    ) -> Result<(), String> {
+        if name.trim().is_empty() || RESERVED_NAMES.contains(&name) {
+            return Err(format!("Invalid view name: '{}'", name));
+        }
+        if let Some(filter) = &self.filter {
+            Query::parse(filter, settings)?;
+        }
+        if let Some(list) = self.lists.iter().find(|list| !known_lists.contains(list)) {
+            return Err(format!("Task list {} does not exist", list));
+        }
+        Ok(())
+    }
+}
+
+/// Show `view` for every one of `lists`, given as (name, path) pairs. Views
+/// saved without a sort order use `default_sort`.
+pub fn show_view(
+    name: &str,
+    view: &View,
+    lists: &[(String, String)],
+    default_sort: &[SortKey],
+    settings: &DateSettings,
+) -> Result<(), String> {
+    let filter = Query::parse_where(view.filter.clone(), settings)?;
+    let sort = if view.sort.is_empty() {
+        default_sort
+    } else {
+        &view.sort
+    };
+    let columns = if view.columns.is_empty() {
+        &list::DEFAULT_COLUMNS[..]
+    } else {
+        &view.columns
+    };
+
+    for (list_name, path) in lists {
+        let store = store::open_store(path)?;
+        let tasks = store.query(&filter)?;
+        let related = store::load_related(store.as_ref(), &tasks)?;
+        list::list_tasks(
+            tasks,
+            &related,
+            view.count.unwrap_or(DEFAULT_COUNT),
+            format!("{} in {}", name, list_name),
+            sort,
+            view.reverse,
+            columns,
+            settings,
+        );
+    }
+    Ok(())
+}
+
+/// Comma separated names of clap values, as they are typed on the command line.
+pub fn value_names<T: ValueEnum>(values: &[T]) -> String {
+    values
+        .iter()
+        .filter_map(|value| value.to_possible_value())
+        .map(|value| value.get_name().to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+pub fn print_views<'a>(views: impl Iterator<Item = (&'a String, &'a View)>) {
+    let mut table = Table::new();
+    table.set_titles(row!["Name", "Filter", "Sort", "Columns", "Count", "Lists"]);
+    for (name, view) in views {
+        let lists = if view.all_lists {
+            String::from("all")
+        } else if view.lists.is_empty() {
+            String::from("current")
+        } else {
+            view.lists.join(",")
+        };
+        let mut sort = if view.sort.is_empty() {
+            String::from("default")
+        } else {
+            value_names(&view.sort)
+        };
+        if view.reverse {
+            sort.push_str(" (reversed)");
+        }
+        table.add_row(row![
+            name,
+            view.filter.clone().unwrap_or_default(),
+            sort,
+            value_names(&view.columns),
+            view.count.map(|count| count.to_string()).unwrap_or_default(),
+            lists
+        ]);
+    }
+    table.printstd();
+}